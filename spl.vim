@@ -4,12 +4,15 @@ if exists("b:current_syntax")
 endif
 
 syn match Comment /".*?";/
-syn match Number /\<[0-9._]*\>/
+syn match Comment /\(^\|\s\)\zs\(#\|\/\/\).*$/
+syn region Comment start=/\(^\|\s\)\zs\/\*/ end=/\*\//
+syn keyword Boolean true false
+syn match Number /\<-\?\(0x[0-9a-fA-F_]\+\|0o[0-7_]\+\|0b[01_]\+\|[0-9][0-9._]*\)[ilnfm]\?\>/
 syn match Function /\<func[ \n]\+[^ ]\+[ \n]\+{ .*[ ]*|\|{ .*[ ]*|\|{\|}/
-syn keyword Keyword while if exit eq lt gt neg or and not + - * ++ -- % / with namespace catch use
+syn keyword Keyword while for in break continue return if else elif match exit eq lt gt neg or and not + - * ++ -- % / with namespace catch finally use include super interface enum gen yield async await freeze
 syn match Keyword /;/
 syn keyword Type pop dup swap
 syn match Type /=[a-zA-Z0-9_\-]\+\|\<_[a-zA-Z0-9_\-]\+\>/
 syn match Identifier /[a-zA-Z0-9_\-]\+:\|\<this\>/
 syn match String /"[^"]*"/
-syn match Typedef /\<def[ \n]\+[^ ]*\|construct/
+syn match Typedef /\<\(def\|const\)[ \n]\+[^ ]*\|construct\|interface\|enum/
@@ -0,0 +1,475 @@
+//! Numbers without a fixed size: [BigInt], which `mega` arithmetic is promoted to when it
+//! overflows, and [Decimal], for exact calculations with decimal fractions, like money.
+
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter},
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// An integer of any size, stored as its sign and its magnitude in base 2^32, least significant
+/// digit first and without leading zeros, so every value has exactly one representation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+fn trim(mut digits: Vec<u32>) -> Vec<u32> {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    digits
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut out = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+    for (i, x) in a.iter().enumerate() {
+        let sum = *x as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        out.push(sum as u32);
+        carry = sum >> 32;
+    }
+    out.push(carry as u32);
+    trim(out)
+}
+
+/// Subtracts the magnitude b from a, which must not be smaller.
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, x) in a.iter().enumerate() {
+        let mut diff = *x as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        out.push(diff as u32);
+    }
+    trim(out)
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = vec![0; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, y) in b.iter().enumerate() {
+            let product = *x as u64 * *y as u64 + out[i + j] as u64 + carry;
+            out[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        out[i + b.len()] = carry as u32;
+    }
+    trim(out)
+}
+
+/// Divides the magnitude a by a single digit, returning the quotient and the remainder.
+fn div_rem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut out = vec![0; a.len()];
+    let mut rem = 0;
+    for i in (0..a.len()).rev() {
+        let cur = (rem << 32) | a[i] as u64;
+        out[i] = (cur / divisor as u64) as u32;
+        rem = cur % divisor as u64;
+    }
+    (trim(out), rem as u32)
+}
+
+/// Divides the magnitude a by b, which must not be zero, one bit at a time, returning the quotient
+/// and the remainder.
+fn div_rem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_mag(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+    if b.len() == 1 {
+        let (q, r) = div_rem_small(a, b[0]);
+        return (q, trim(vec![r]));
+    }
+    let mut q = vec![0; a.len()];
+    let mut r = Vec::new();
+    for i in (0..a.len() * 32).rev() {
+        // r = r << 1 | the current bit of a
+        let mut carry = (a[i / 32] >> (i % 32)) & 1;
+        for digit in r.iter_mut() {
+            let next = *digit >> 31;
+            *digit = (*digit << 1) | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            r.push(carry);
+        }
+        if cmp_mag(&r, b) != Ordering::Less {
+            r = sub_mag(&r, b);
+            q[i / 32] |= 1 << (i % 32);
+        }
+    }
+    (trim(q), r)
+}
+
+impl BigInt {
+    fn new(negative: bool, digits: Vec<u32>) -> BigInt {
+        let digits = trim(digits);
+        BigInt {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::new(false, self.digits.clone())
+    }
+
+    /// 10 to the power of n.
+    pub fn pow10(n: u32) -> BigInt {
+        (0..n).fold(BigInt::from(1), |acc, _| acc * BigInt::from(10))
+    }
+
+    /// Converts this to an i128, if it fits in one.
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.digits.len() > 4 {
+            return None;
+        }
+        let mut mag = 0u128;
+        for (i, digit) in self.digits.iter().enumerate() {
+            mag |= (*digit as u128) << (32 * i);
+        }
+        if self.negative {
+            // -2^127 fits, but 2^127 doesn't
+            (mag <= i128::MIN.unsigned_abs()).then(|| (mag as i128).wrapping_neg())
+        } else {
+            mag.try_into().ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Parses digits in the given radix, optionally preceded by a `-`.
+    ///
+    /// ```
+    /// # use spl::bignum::BigInt;
+    /// let x = BigInt::from_str_radix("-ffffffffffffffffffffffffffffffffff", 16).unwrap();
+    /// assert_eq!(x.to_string(), "-87112285931760246646623899502532662132735");
+    /// assert_eq!(x.to_i128(), None);
+    /// ```
+    pub fn from_str_radix(s: &str, radix: u32) -> Option<BigInt> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        if s.is_empty() {
+            return None;
+        }
+        let mut digits = Vec::new();
+        for c in s.chars() {
+            digits = add_mag(&mul_mag(&digits, &[radix]), &[c.to_digit(radix)?]);
+        }
+        Some(BigInt::new(negative, digits))
+    }
+
+    /// Divides with the quotient rounded towards zero and the remainder having the sign of self,
+    /// like the operators of i128 do. Returns None when dividing by zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (q, r) = div_rem_mag(&self.digits, &other.digits);
+        Some((
+            BigInt::new(self.negative != other.negative, q),
+            BigInt::new(self.negative, r),
+        ))
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(x: i128) -> BigInt {
+        let mag = x.unsigned_abs();
+        BigInt::new(x < 0, (0..4).map(|i| (mag >> (32 * i)) as u32).collect())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.digits, &other.digits),
+            (true, true) => cmp_mag(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.digits)
+    }
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_mag(&self.digits, &other.digits));
+        }
+        match cmp_mag(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::new(other.negative, sub_mag(&other.digits, &self.digits)),
+            _ => BigInt::new(self.negative, sub_mag(&self.digits, &other.digits)),
+        }
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: BigInt) -> BigInt {
+        self + -other
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_mag(&self.digits, &other.digits),
+        )
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // split into chunks of 9 decimal digits, least significant first
+        let mut chunks = Vec::new();
+        let mut digits = self.digits.clone();
+        while !digits.is_empty() {
+            let (q, r) = div_rem_small(&digits, 1_000_000_000);
+            chunks.push(r);
+            digits = q;
+        }
+        let mut s = if self.negative { "-" } else { "" }.to_owned();
+        s += &chunks.pop().unwrap_or(0).to_string();
+        for chunk in chunks.iter().rev() {
+            s += &format!("{chunk:09}");
+        }
+        f.write_str(&s)
+    }
+}
+
+/// The amount of digits after the point that division adds when the result doesn't fit in the
+/// scale of its operands.
+pub const DIV_DIGITS: u32 = 16;
+
+/// A decimal number, stored exactly as `mantissa / 10^scale`. The scale is the amount of digits
+/// after the point: addition and subtraction keep the larger one and multiplication adds them up,
+/// like when calculating by hand, so `1.50m 2 *` is `3.00m`. Division adds up to [DIV_DIGITS]
+/// digits and rounds the rest towards zero.
+///
+/// ```
+/// # use spl::bignum::Decimal;
+/// let x = Decimal::parse("0.1").unwrap() + Decimal::parse("0.2").unwrap();
+/// assert_eq!(x.to_string(), "0.3");
+/// let third = Decimal::parse("1").unwrap().checked_div(&Decimal::parse("3").unwrap());
+/// assert_eq!(third.unwrap().to_string(), "0.3333333333333333");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Decimal {
+    mantissa: BigInt,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn new(mantissa: BigInt, scale: u32) -> Decimal {
+        Decimal { mantissa, scale }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa.is_negative()
+    }
+
+    /// The mantissa at a scale that is at least the current one.
+    fn mantissa_at(&self, scale: u32) -> BigInt {
+        self.mantissa.clone() * BigInt::pow10(scale - self.scale)
+    }
+
+    /// Both mantissas at the larger of the two scales, and that scale.
+    fn aligned(&self, other: &Decimal) -> (BigInt, BigInt, u32) {
+        let scale = self.scale.max(other.scale);
+        (self.mantissa_at(scale), other.mantissa_at(scale), scale)
+    }
+
+    /// Removes zeros at the end of the digits after the point, keeping at least `min_scale` of
+    /// them.
+    fn normalized(mut self, min_scale: u32) -> Decimal {
+        let ten = BigInt::from(10);
+        while self.scale > min_scale {
+            let Some((q, r)) = self.mantissa.div_rem(&ten) else {
+                unreachable!()
+            };
+            if !r.is_zero() {
+                break;
+            }
+            self.mantissa = q;
+            self.scale -= 1;
+        }
+        self
+    }
+
+    /// The integer part, rounding towards zero.
+    pub fn trunc(&self) -> BigInt {
+        self.mantissa.div_rem(&BigInt::pow10(self.scale)).unwrap().0
+    }
+
+    /// Rounds half away from zero, or pads with zeros, to exactly `places` digits after the point.
+    pub fn round(&self, places: u32) -> Decimal {
+        if self.scale <= places {
+            return Decimal::new(self.mantissa_at(places), places);
+        }
+        let divisor = BigInt::pow10(self.scale - places);
+        let (q, r) = self.mantissa.div_rem(&divisor).unwrap();
+        let q = match r.abs() * BigInt::from(2) < divisor {
+            true => q,
+            false if self.is_negative() => q - BigInt::from(1),
+            false => q + BigInt::from(1),
+        };
+        Decimal::new(q, places)
+    }
+
+    /// Returns None when dividing by zero.
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        let target = scale + DIV_DIGITS;
+        let n = self.mantissa.clone() * BigInt::pow10(target + other.scale - self.scale);
+        let (q, _) = n.div_rem(&other.mantissa)?;
+        Some(Decimal::new(q, target).normalized(scale))
+    }
+
+    /// The remainder of dividing with the quotient rounded towards zero, which has the sign of
+    /// self. Returns None when dividing by zero.
+    pub fn checked_rem(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other);
+        Some(Decimal::new(a.div_rem(&b)?.1, scale))
+    }
+
+    /// Parses a decimal number like `-12.50`.
+    pub fn parse(s: &str) -> Option<Decimal> {
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        if frac.starts_with(['-', '+']) || (s.contains('.') && frac.is_empty()) {
+            return None;
+        }
+        Some(Decimal::new(
+            BigInt::from_str_radix(&(int.to_owned() + frac), 10)?,
+            frac.len() as u32,
+        ))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+}
+
+impl From<BigInt> for Decimal {
+    fn from(x: BigInt) -> Decimal {
+        Decimal::new(x, 0)
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let (a, b, _) = self.aligned(other);
+        a.cmp(&b)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal::new(-self.mantissa, self.scale)
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        let (a, b, scale) = self.aligned(&other);
+        Decimal::new(a + b, scale)
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        self + -other
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Decimal) -> Decimal {
+        Decimal::new(self.mantissa * other.mantissa, self.scale + other.scale)
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let scale = self.scale as usize;
+        let mut digits = self.mantissa.abs().to_string();
+        if digits.len() <= scale {
+            digits = "0".repeat(scale + 1 - digits.len()) + &digits;
+        }
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        let sign = if self.is_negative() { "-" } else { "" };
+        match scale {
+            0 => write!(f, "{sign}{int}"),
+            _ => write!(f, "{sign}{int}.{frac}"),
+        }
+    }
+}
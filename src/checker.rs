@@ -0,0 +1,866 @@
+//! Static stack-effect analysis, used by `spl check`.
+//!
+//! The checker simulates the height of the stack through [Words] to find code that obviously
+//! doesn't do what its declarations say. It is conservative: as soon as something can't be known
+//! statically (calling a function value, `stop`, a loop that changes the stack height, ...), it
+//! stops analysing that function instead of guessing.
+//!
+//! It warns when:
+//! - a function or method body leaves a different amount of values than it declares.
+//! - `with a b ;` pops more values than are available.
+//! - a call with `;` discards the result of a function that doesn't return anything.
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    lexer::lex, parse_format, stdlib, Binding, Field, FormatPiece, FuncImpl, Keyword, Pattern,
+    Signature, TypeSpec, Value, Word, Words,
+};
+
+/// How a function changes the stack: it takes `pops` values and then leaves `pushes` values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackEffect {
+    pub pops: u32,
+    pub pushes: u32,
+}
+
+impl StackEffect {
+    pub const fn new(pops: u32, pushes: u32) -> Self {
+        StackEffect { pops, pushes }
+    }
+}
+
+/// The stack effects of the native functions. Functions that take or leave a variable amount of
+/// values, or that run other code, are not listed.
+pub const NATIVE_EFFECTS: &[(&str, StackEffect)] = &[
+    ("pop", StackEffect::new(1, 0)),
+    ("dup", StackEffect::new(1, 2)),
+    ("clone", StackEffect::new(1, 1)),
+    ("freeze", StackEffect::new(1, 1)),
+    ("frozen?", StackEffect::new(1, 1)),
+    ("swap", StackEffect::new(2, 2)),
+    ("print", StackEffect::new(1, 0)),
+    ("gettype", StackEffect::new(1, 1)),
+    ("mro", StackEffect::new(1, 1)),
+    ("implements?", StackEffect::new(2, 1)),
+    ("settype", StackEffect::new(2, 1)),
+    ("anew", StackEffect::new(1, 1)),
+    ("array-len", StackEffect::new(1, 1)),
+    ("array-get", StackEffect::new(2, 1)),
+    ("array-set", StackEffect::new(3, 1)),
+    ("bnew", StackEffect::new(1, 1)),
+    ("bytes-len", StackEffect::new(1, 1)),
+    ("bytes-get", StackEffect::new(2, 1)),
+    ("bytes-set", StackEffect::new(3, 1)),
+    ("bytes-sub", StackEffect::new(3, 1)),
+    ("bytes-concat", StackEffect::new(2, 1)),
+    ("bytes-to-hex", StackEffect::new(1, 1)),
+    ("hex-to-bytes", StackEffect::new(1, 1)),
+    ("bytes-read-uint", StackEffect::new(4, 1)),
+    ("bytes-write-uint", StackEffect::new(5, 0)),
+    ("eq", StackEffect::new(2, 1)),
+    ("lt", StackEffect::new(2, 1)),
+    ("gt", StackEffect::new(2, 1)),
+    ("not", StackEffect::new(1, 1)),
+    ("and", StackEffect::new(2, 1)),
+    ("or", StackEffect::new(2, 1)),
+    ("+", StackEffect::new(2, 1)),
+    ("-", StackEffect::new(2, 1)),
+    ("/", StackEffect::new(2, 1)),
+    ("*", StackEffect::new(2, 1)),
+    ("%", StackEffect::new(2, 1)),
+    ("_int", StackEffect::new(1, 1)),
+    ("_long", StackEffect::new(1, 1)),
+    ("_mega", StackEffect::new(1, 1)),
+    ("_float", StackEffect::new(1, 1)),
+    ("_double", StackEffect::new(1, 1)),
+    ("_bigint", StackEffect::new(1, 1)),
+    ("_decimal", StackEffect::new(1, 1)),
+    ("_bool", StackEffect::new(1, 1)),
+    ("_array", StackEffect::new(1, 1)),
+    ("_bytes", StackEffect::new(1, 1)),
+    ("_str", StackEffect::new(1, 1)),
+    ("debug-str", StackEffect::new(1, 1)),
+    ("trace", StackEffect::new(0, 1)),
+    ("mr-trace", StackEffect::new(0, 1)),
+    ("argv", StackEffect::new(0, 1)),
+    ("get-env", StackEffect::new(0, 1)),
+    ("read-file", StackEffect::new(1, 1)),
+    ("import", StackEffect::new(1, 0)),
+    ("readln", StackEffect::new(0, 1)),
+    ("command", StackEffect::new(1, 0)),
+    ("command-wait", StackEffect::new(1, 1)),
+    ("time-millis", StackEffect::new(0, 1)),
+    ("str-to-bytes", StackEffect::new(1, 1)),
+    ("bytes-to-str", StackEffect::new(1, 1)),
+    ("acopy", StackEffect::new(5, 1)),
+    ("write-sasm", StackEffect::new(1, 1)),
+    ("write-file-sasm", StackEffect::new(1, 1)),
+    ("dyn-__dump", StackEffect::new(0, 0)),
+    ("dyn-def", StackEffect::new(1, 0)),
+    ("dyn-func", StackEffect::new(2, 0)),
+    ("dyn-construct", StackEffect::new(1, 0)),
+    ("dyn-namespace", StackEffect::new(1, 0)),
+    ("dyn-def-field", StackEffect::new(2, 0)),
+    ("dyn-def-method", StackEffect::new(3, 0)),
+    ("dyn-include", StackEffect::new(2, 0)),
+    ("dyn-all-types", StackEffect::new(0, 1)),
+    ("dyn-read", StackEffect::new(1, 1)),
+    ("dyn-readf", StackEffect::new(2, 1)),
+    ("dyn-sasm", StackEffect::new(1, 1)),
+    ("dyn-sasmf", StackEffect::new(2, 1)),
+    ("dyn-use", StackEffect::new(1, 0)),
+    ("write-stream", StackEffect::new(2, 1)),
+    ("write-all-stream", StackEffect::new(2, 0)),
+    ("flush-stream", StackEffect::new(1, 0)),
+    ("read-stream", StackEffect::new(2, 2)),
+    ("read-all-stream", StackEffect::new(2, 1)),
+    ("close-stream", StackEffect::new(1, 0)),
+    ("poll-streams", StackEffect::new(3, 1)),
+    ("new-generator", StackEffect::new(1, 1)),
+    ("yield", StackEffect::new(1, 0)),
+];
+
+/// Functions after which execution never continues.
+pub const DIVERGING: &[&str] = &["throw", "rethrow", "exit", "panic"];
+
+/// The amount of values a signature returns, unless it is variadic (`..`).
+fn fixed_returns(signature: &Signature) -> Option<u32> {
+    (!signature.returns.contains(&TypeSpec::Rest)).then(|| signature.ret_count())
+}
+
+/// A problem found by the [Checker].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// Where the problem is, for example `func main` or `method List:push`.
+    pub location: String,
+    pub message: String,
+}
+
+/// The kinds of problems the [Checker] finds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A function or method body leaves a different amount of values than it declares.
+    ReturnCount { declared: u32, leaves: i64 },
+    /// `with` pops more values than are available.
+    WithUnderflow,
+    /// A call with `;` discards the result of something that doesn't return anything.
+    DiscardsNothing,
+    /// A match pattern destructures more fields than the enum variant it names has.
+    PatternArity { fields: usize, names: usize },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.location)?;
+        f.write_str(": ")?;
+        f.write_str(&self.message)
+    }
+}
+
+/// What is known about a function or method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Known {
+    /// The stack effect, if it is known and the same for every definition.
+    effect: Option<StackEffect>,
+    /// The amount of returned values, if it is the same for every definition.
+    returns: Option<u32>,
+}
+
+impl Known {
+    fn merge(self, other: Known) -> Known {
+        Known {
+            effect: self.effect.filter(|_| self.effect == other.effect),
+            returns: self.returns.filter(|_| self.returns == other.returns),
+        }
+    }
+}
+
+/// The result of simulating a block.
+enum Flow {
+    /// The block ends with the stack at `height`, after having been at `min` at its lowest.
+    Known { height: i64, min: i64 },
+    /// The stack height can't be known statically.
+    Unknown,
+    /// The block never finishes normally.
+    Diverges,
+}
+
+impl Flow {
+    /// The flow after one of two branches, which must end at the same height unless one diverges.
+    fn join(self, other: Flow) -> Flow {
+        match (self, other) {
+            (
+                Flow::Known {
+                    height: ha,
+                    min: ma,
+                },
+                Flow::Known {
+                    height: hb,
+                    min: mb,
+                },
+            ) if ha == hb => Flow::Known {
+                height: ha,
+                min: ma.min(mb),
+            },
+            (known @ Flow::Known { .. }, Flow::Diverges)
+            | (Flow::Diverges, known @ Flow::Known { .. }) => known,
+            (Flow::Diverges, Flow::Diverges) => Flow::Diverges,
+            _ => Flow::Unknown,
+        }
+    }
+}
+
+/// Checks the stack effects of SPL code.
+///
+/// ```
+/// let mut checker = spl::checker::Checker::with_std();
+/// let words = spl::lex("func two { mega mega | 1 }".to_owned()).unwrap();
+/// assert_eq!(checker.check(&words).len(), 1);
+///
+/// let source = "enum Shape { Circle(r) ; } 1 Shape:Circle:new match { Shape:Circle(r h) { } }";
+/// let warnings = checker.check(&spl::lex(source.to_owned()).unwrap());
+/// assert_eq!(warnings[0].kind, spl::checker::WarningKind::PatternArity { fields: 1, names: 2 });
+/// ```
+pub struct Checker {
+    functions: HashMap<String, Known>,
+    methods: HashMap<String, Known>,
+    /// The amount of fields of each enum variant, by its full name.
+    variants: HashMap<String, usize>,
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checker {
+    /// Creates a checker that only knows the native functions.
+    pub fn new() -> Self {
+        Checker {
+            functions: NATIVE_EFFECTS
+                .iter()
+                .map(|(name, effect)| {
+                    (
+                        name.to_string(),
+                        Known {
+                            effect: Some(*effect),
+                            returns: Some(effect.pushes),
+                        },
+                    )
+                })
+                .collect(),
+            methods: HashMap::new(),
+            variants: HashMap::new(),
+        }
+    }
+
+    /// Creates a checker that knows the native functions and everything defined by the embedded
+    /// standard library.
+    pub fn with_std() -> Self {
+        let mut checker = Self::new();
+        for (_, source) in stdlib::FILES {
+            if let Ok(words) = lex(source.to_string()) {
+                checker.learn(&words);
+            }
+        }
+        checker
+    }
+
+    /// Learns the functions, methods, fields and variables defined in some code, so calls to them
+    /// can be checked.
+    pub fn learn(&mut self, words: &Words) {
+        let mut definitions = Vec::new();
+        self.collect(words, &mut definitions);
+        // functions declaring their arguments with `with` are known right away. the others are
+        // inferred once everything else is known.
+        let mut infer = Vec::new();
+        for (name, returns, body, is_method) in definitions {
+            let Some(returns) = returns else {
+                self.define(&name, is_method, None, None);
+                continue;
+            };
+            let effect = match body.words.first() {
+                Some(Word::Key(Keyword::With(bindings))) => {
+                    Some(StackEffect::new(bindings.len() as u32, returns))
+                }
+                _ => {
+                    infer.push((name.clone(), returns, body, is_method));
+                    None
+                }
+            };
+            self.define(&name, is_method, effect, Some(returns));
+        }
+        for (name, returns, body, is_method) in infer {
+            if let Flow::Known { min, .. } = self.simulate_quiet(&body) {
+                let pops = if is_method { min.min(-1) } else { min };
+                self.define(
+                    &name,
+                    is_method,
+                    Some(StackEffect::new(-pops as u32, returns)),
+                    Some(returns),
+                );
+            }
+        }
+    }
+
+    fn define(
+        &mut self,
+        name: &str,
+        is_method: bool,
+        effect: Option<StackEffect>,
+        returns: Option<u32>,
+    ) {
+        let known = Known { effect, returns };
+        if is_method {
+            let known = match self.methods.get(name) {
+                Some(old) if old.effect.is_some() || effect.is_none() => old.merge(known),
+                _ => known,
+            };
+            self.methods.insert(name.to_owned(), known);
+        } else {
+            self.functions.insert(name.to_owned(), known);
+        }
+    }
+
+    fn define_var(&mut self, name: &str) {
+        self.define(name, false, Some(StackEffect::new(0, 1)), Some(1));
+        self.define(
+            &("=".to_owned() + name),
+            false,
+            Some(StackEffect::new(1, 0)),
+            Some(0),
+        );
+    }
+
+    fn collect(
+        &mut self,
+        words: &Words,
+        definitions: &mut Vec<(String, Option<u32>, Words, bool)>,
+    ) {
+        for word in &words.words {
+            match word {
+                Word::Key(Keyword::Def(name)) => self.define_var(name),
+                Word::Key(Keyword::Const(name, value)) => {
+                    self.define_var(name);
+                    self.collect(value, definitions);
+                }
+                Word::Key(Keyword::With(bindings)) => bindings
+                    .iter()
+                    .flat_map(Binding::names)
+                    .for_each(|x| self.define_var(&x)),
+                Word::Key(Keyword::Use(item)) => {
+                    self.define_var(item.rsplit(':').next().unwrap_or(item))
+                }
+                Word::Key(Keyword::For(var, iterable, blk)) => {
+                    self.define_var(var);
+                    self.collect(iterable, definitions);
+                    self.collect(blk, definitions);
+                }
+                Word::Key(Keyword::Func(name, returns, body)) => {
+                    definitions.push((name.clone(), fixed_returns(returns), body.clone(), false));
+                    self.collect(body, definitions);
+                }
+                Word::Key(
+                    key @ (Keyword::Construct(name, _, methods, _)
+                    | Keyword::Interface(name, _, methods)
+                    | Keyword::Enum(name, _, methods)),
+                ) => {
+                    if !name.contains(':') {
+                        self.define_var(name);
+                    }
+                    let fields: Vec<&Field> = match key {
+                        Keyword::Construct(_, fields, ..) => fields.iter().collect(),
+                        Keyword::Enum(_, variants, _) => {
+                            for (variant, fields) in variants {
+                                self.define(variant, true, Some(StackEffect::new(1, 1)), Some(1));
+                                self.variants
+                                    .insert(format!("{name}:{variant}"), fields.len());
+                            }
+                            self.define("is-variant?", true, Some(StackEffect::new(2, 1)), Some(1));
+                            variants.iter().flat_map(|x| &x.1).collect()
+                        }
+                        _ => Vec::new(),
+                    };
+                    for field in fields {
+                        self.define(&field.name, true, Some(StackEffect::new(1, 1)), Some(1));
+                        self.define(
+                            &("=".to_owned() + &field.name),
+                            true,
+                            Some(StackEffect::new(2, 0)),
+                            Some(0),
+                        );
+                    }
+                    for (method, (returns, body)) in methods {
+                        definitions.push((
+                            method.clone(),
+                            fixed_returns(returns),
+                            body.clone(),
+                            true,
+                        ));
+                        self.collect(body, definitions);
+                    }
+                }
+                Word::Key(key) => {
+                    if let Keyword::Match(arms, _) = key {
+                        for (pattern, _) in arms {
+                            if let Pattern::Destructure(_, names) = pattern {
+                                names.iter().for_each(|x| self.define_var(x));
+                            }
+                        }
+                    }
+                    key.blocks()
+                        .into_iter()
+                        .for_each(|blk| self.collect(blk, definitions))
+                }
+                Word::Const(Value::Func(f)) => {
+                    if let FuncImpl::SPL(ref body) = f.to_call {
+                        self.collect(body, definitions);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Checks some code, returning all problems found. Everything the code defines is learned
+    /// first, so the order of definitions doesn't matter.
+    pub fn check(&mut self, words: &Words) -> Vec<Warning> {
+        self.learn(words);
+        let mut warnings = Vec::new();
+        Simulation {
+            checker: self,
+            warnings: Some(&mut warnings),
+            location: "top level".to_owned(),
+            floor: Some(0),
+            markers: Vec::new(),
+            returned: Vec::new(),
+        }
+        .block(words, 0, 0);
+        self.walk(words, "top level", &mut warnings);
+        warnings
+    }
+
+    fn simulate_quiet(&self, body: &Words) -> Flow {
+        let mut simulation = Simulation {
+            checker: self,
+            warnings: None,
+            location: String::new(),
+            floor: None,
+            markers: Vec::new(),
+            returned: Vec::new(),
+        };
+        let flow = simulation.block(body, 0, 0);
+        // returning early ends the function just like reaching the end of it does.
+        simulation
+            .returned
+            .into_iter()
+            .fold(flow, |flow, (height, min)| match flow {
+                Flow::Known { height, min: m } => Flow::Known {
+                    height,
+                    min: m.min(min),
+                },
+                Flow::Diverges => Flow::Known { height, min },
+                Flow::Unknown => Flow::Unknown,
+            })
+    }
+
+    /// Checks a function body, including whether it leaves as many values as it declares.
+    fn function(
+        &self,
+        body: &Words,
+        returns: Option<u32>,
+        is_method: bool,
+        location: String,
+        warnings: &mut Vec<Warning>,
+    ) {
+        let mut simulation = Simulation {
+            checker: self,
+            warnings: Some(warnings),
+            location: location.clone(),
+            floor: None,
+            markers: Vec::new(),
+            returned: Vec::new(),
+        };
+        let flow = simulation.block(body, 0, 0);
+        let mut ends = simulation.returned;
+        if let Flow::Known { height, min } = flow {
+            ends.push((height, min));
+        }
+        if let Some(returns) = returns {
+            for (height, min) in ends {
+                let min = if is_method { min.min(-1) } else { min };
+                let leaves = height - min;
+                if leaves != returns as i64 {
+                    warnings.push(Warning {
+                        kind: WarningKind::ReturnCount {
+                            declared: returns,
+                            leaves,
+                        },
+                        location: location.clone(),
+                        message: format!("declares {returns} return values, but leaves {leaves}"),
+                    });
+                }
+            }
+        }
+        self.walk(body, &location, warnings);
+    }
+
+    /// Looks for function definitions and discarded results in nested code.
+    fn walk(&self, words: &Words, location: &str, warnings: &mut Vec<Warning>) {
+        for word in &words.words {
+            match word {
+                Word::Call(name, true, 0) => {
+                    if let Some(Known {
+                        returns: Some(0), ..
+                    }) = self.functions.get(name)
+                    {
+                        warnings.push(Warning {
+                            kind: WarningKind::DiscardsNothing,
+                            location: location.to_owned(),
+                            message: format!("`{name};` discards the result of a function that doesn't return anything"),
+                        });
+                    }
+                }
+                Word::ObjCall(name, true, 0) => {
+                    if let Some(Known {
+                        returns: Some(0), ..
+                    }) = self.methods.get(name)
+                    {
+                        warnings.push(Warning {
+                            kind: WarningKind::DiscardsNothing,
+                            location: location.to_owned(),
+                            message: format!("`:{name};` discards the result of a method that doesn't return anything"),
+                        });
+                    }
+                }
+                Word::Const(Value::Func(f)) => {
+                    if let FuncImpl::SPL(ref body) = f.to_call {
+                        self.function(body, None, false, format!("{location} > lambda"), warnings);
+                    }
+                }
+                Word::Key(Keyword::Func(name, returns, body)) => {
+                    self.function(
+                        body,
+                        fixed_returns(returns),
+                        false,
+                        format!("func {name}"),
+                        warnings,
+                    );
+                }
+                Word::Key(
+                    Keyword::Construct(name, _, methods, _)
+                    | Keyword::Interface(name, _, methods)
+                    | Keyword::Enum(name, _, methods),
+                ) => {
+                    for (method, (returns, body)) in methods {
+                        if body.words.is_empty() {
+                            continue;
+                        }
+                        self.function(
+                            body,
+                            fixed_returns(returns),
+                            true,
+                            format!("method {name}:{method}"),
+                            warnings,
+                        );
+                    }
+                }
+                Word::Key(key) => {
+                    if let Keyword::Match(arms, _) = key {
+                        for (pattern, _) in arms {
+                            self.check_arity(pattern, location, warnings);
+                        }
+                    }
+                    key.blocks()
+                        .into_iter()
+                        .for_each(|blk| self.walk(blk, location, warnings))
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Warns about a pattern destructuring more fields than the enum variant it names has, if the
+    /// variant is known.
+    fn check_arity(&self, pattern: &Pattern, location: &str, warnings: &mut Vec<Warning>) {
+        let Pattern::Destructure(words, names) = pattern else {
+            return;
+        };
+        let mut variant = Vec::new();
+        for word in &words.words {
+            match word {
+                Word::Call(name, false, 0) if variant.is_empty() => variant.push(name.as_str()),
+                Word::ObjCall(name, false, 0) if !variant.is_empty() => variant.push(name),
+                _ => return,
+            }
+        }
+        let variant = variant.join(":");
+        if let Some(&fields) = self.variants.get(&variant) {
+            if names.len() > fields {
+                warnings.push(Warning {
+                    kind: WarningKind::PatternArity {
+                        fields,
+                        names: names.len(),
+                    },
+                    location: location.to_owned(),
+                    message: format!(
+                        "`{variant}(...)` destructures {} fields, but the variant only has {fields}",
+                        names.len()
+                    ),
+                });
+            }
+        }
+    }
+}
+
+struct Simulation<'a> {
+    checker: &'a Checker,
+    warnings: Option<&'a mut Vec<Warning>>,
+    location: String,
+    /// The height below which nothing is available, if known.
+    floor: Option<i64>,
+    /// The heights at which `[` was called.
+    markers: Vec<i64>,
+    /// The heights and minimums at which `return` was used.
+    returned: Vec<(i64, i64)>,
+}
+
+impl Simulation<'_> {
+    fn block(&mut self, words: &Words, mut height: i64, mut min: i64) -> Flow {
+        macro_rules! apply {
+            ($effect:expr) => {{
+                let effect: StackEffect = $effect;
+                height -= effect.pops as i64;
+                min = min.min(height);
+                height += effect.pushes as i64;
+            }};
+        }
+        for (i, word) in words.words.iter().enumerate() {
+            match word {
+                Word::Const(_) => height += 1,
+                Word::Call(_, _, ra) | Word::ObjCall(_, _, ra) if *ra != 0 => {
+                    if let Word::ObjCall(..) = word {
+                        apply!(StackEffect::new(1, 1));
+                    } else {
+                        height += 1;
+                    }
+                }
+                Word::Call(name, rem, _) => match name.as_str() {
+                    "[" => {
+                        self.markers.push(height);
+                        height += 1;
+                    }
+                    "]" => {
+                        let Some(marker) = self.markers.pop() else {
+                            return Flow::Unknown;
+                        };
+                        height = marker + 1;
+                    }
+                    // the amount of arguments is known if the format string is a constant, which
+                    // it always is in f-strings.
+                    "format" => {
+                        let Some(Word::Const(Value::Str(fmt))) =
+                            i.checked_sub(1).map(|i| &words.words[i])
+                        else {
+                            return Flow::Unknown;
+                        };
+                        let Some(pieces) = parse_format(fmt) else {
+                            return Flow::Unknown;
+                        };
+                        let args = pieces
+                            .iter()
+                            .filter(|x| matches!(x, FormatPiece::Arg(_)))
+                            .count();
+                        apply!(StackEffect::new(args as u32 + 1, 1));
+                        if *rem {
+                            height -= 1;
+                        }
+                    }
+                    x if DIVERGING.contains(&x) => return Flow::Diverges,
+                    x => {
+                        let Some(effect) = self.checker.functions.get(x).and_then(|x| x.effect)
+                        else {
+                            return Flow::Unknown;
+                        };
+                        apply!(effect);
+                        if *rem {
+                            height -= effect.pushes as i64;
+                        }
+                    }
+                },
+                // `Type:new` passes all of its arguments on to the constructor, whichever it is.
+                Word::ObjCall(name, ..) if name == "new" => return Flow::Unknown,
+                Word::ObjCall(name, rem, _) => {
+                    let Some(effect) = self.checker.methods.get(name).and_then(|x| x.effect) else {
+                        return Flow::Unknown;
+                    };
+                    apply!(effect);
+                    if *rem {
+                        height -= effect.pushes as i64;
+                    }
+                }
+                Word::Key(key) => match key {
+                    Keyword::Dump
+                    | Keyword::Def(_)
+                    | Keyword::Func(..)
+                    | Keyword::Construct(..)
+                    | Keyword::Interface(..)
+                    | Keyword::Enum(..)
+                    | Keyword::Include(..)
+                    | Keyword::Use(_)
+                    | Keyword::FuncOf(..) => (),
+                    Keyword::With(bindings) => {
+                        let n = bindings.len() as i64;
+                        if let Some(floor) = self.floor {
+                            if height - n < floor {
+                                self.warn(
+                                    WarningKind::WithUnderflow,
+                                    format!(
+                                        "`with {} ;` pops {n} values, but only {} are available",
+                                        bindings
+                                            .iter()
+                                            .map(ToString::to_string)
+                                            .collect::<Vec<_>>()
+                                            .join(" "),
+                                        height - floor
+                                    ),
+                                );
+                            }
+                        }
+                        height -= n;
+                        min = min.min(height);
+                        if i == 0 && self.floor.is_none() {
+                            // the arguments of the function have been declared.
+                            self.floor = Some(height);
+                        }
+                    }
+                    Keyword::ObjPush => apply!(StackEffect::new(1, 0)),
+                    Keyword::ObjPop => height += 1,
+                    Keyword::While(cond, blk) => {
+                        match self.block(cond, height, min) {
+                            Flow::Known { height: h, min: m } if h == height + 1 => min = m,
+                            Flow::Diverges => return Flow::Diverges,
+                            _ => return Flow::Unknown,
+                        }
+                        match self.block(blk, height, min) {
+                            Flow::Known { height: h, min: m } if h == height => min = m,
+                            _ => return Flow::Unknown,
+                        }
+                    }
+                    Keyword::If(blk) => {
+                        apply!(StackEffect::new(1, 0));
+                        match self.block(blk, height, min) {
+                            Flow::Known { height: h, min: m } if h == height => min = m,
+                            Flow::Diverges => (),
+                            _ => return Flow::Unknown,
+                        }
+                    }
+                    Keyword::Const(_, value) => match self.block(value, height, min) {
+                        Flow::Known { height: h, min: m } if h == height + 1 => min = m,
+                        Flow::Diverges => return Flow::Diverges,
+                        _ => return Flow::Unknown,
+                    },
+                    Keyword::For(_, iterable, blk) => {
+                        match self.block(iterable, height, min) {
+                            Flow::Known { height: h, min: m } if h == height + 1 => min = m,
+                            Flow::Diverges => return Flow::Diverges,
+                            _ => return Flow::Unknown,
+                        }
+                        match self.block(blk, height, min) {
+                            Flow::Known { height: h, min: m } if h == height => min = m,
+                            _ => return Flow::Unknown,
+                        }
+                    }
+                    Keyword::Super(name, rem) => {
+                        let Some(effect) = self.checker.methods.get(name).and_then(|x| x.effect)
+                        else {
+                            return Flow::Unknown;
+                        };
+                        apply!(effect);
+                        if *rem {
+                            height -= effect.pushes as i64;
+                        }
+                    }
+                    Keyword::Break | Keyword::Continue => return Flow::Diverges,
+                    Keyword::Return => {
+                        self.returned.push((height, min));
+                        return Flow::Diverges;
+                    }
+                    Keyword::IfElse(blk, els) => {
+                        apply!(StackEffect::new(1, 0));
+                        let a = self.block(blk, height, min);
+                        let b = self.block(els, height, min);
+                        match a.join(b) {
+                            Flow::Known { height: h, min: m } => {
+                                height = h;
+                                min = m;
+                            }
+                            flow => return flow,
+                        }
+                    }
+                    Keyword::Match(arms, default) => {
+                        apply!(StackEffect::new(1, 0));
+                        // without a default, not matching anything leaves the stack unchanged.
+                        let mut flow = match default {
+                            Some(default) => self.block(default, height, min),
+                            None => Flow::Known { height, min },
+                        };
+                        for (pattern, blk) in arms {
+                            match self.block(pattern.words(), height, min) {
+                                Flow::Known { height: h, min: m } if h == height + 1 => {
+                                    flow = flow.join(self.block(blk, height, m));
+                                }
+                                Flow::Diverges => (),
+                                _ => return Flow::Unknown,
+                            }
+                        }
+                        match flow {
+                            Flow::Known { height: h, min: m } => {
+                                height = h;
+                                min = m;
+                            }
+                            flow => return flow,
+                        }
+                    }
+                    Keyword::Catch(_, blk, ctch, finally) => {
+                        let a = self.block(blk, height, min);
+                        let b = self.block(ctch, height + 1, min);
+                        match a.join(b) {
+                            Flow::Known { height: h, min: m } => {
+                                height = h;
+                                min = m;
+                            }
+                            flow => return flow,
+                        }
+                        if let Some(finally) = finally {
+                            match self.block(finally, height, min) {
+                                Flow::Known { height: h, min: m } => {
+                                    height = h;
+                                    min = m;
+                                }
+                                flow => return flow,
+                            }
+                        }
+                    }
+                },
+            }
+        }
+        Flow::Known { height, min }
+    }
+
+    fn warn(&mut self, kind: WarningKind, message: String) {
+        if let Some(ref mut warnings) = self.warnings {
+            warnings.push(Warning {
+                kind,
+                location: self.location.clone(),
+                message,
+            });
+        }
+    }
+}
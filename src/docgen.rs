@@ -0,0 +1,655 @@
+//! The SPL documentation generator, used by `spl doc`.
+//!
+//! Documentation is written as comments (`# ...`, `// ...`, `/* ... */` or `"...";`) directly in
+//! front of the documented function, construct, interface, enum, variant, field or method.
+//! Comments at the start of a file that are separated from the code by an empty line document the
+//! whole module. Everything else (names, fields, signatures and `include`s) comes from the
+//! [Keyword] tree.
+
+use std::collections::HashMap;
+
+use crate::{
+    lexer::{lex, tokenize, LexerError, TokenKind},
+    Keyword, Signature, Word, Words,
+};
+
+/// The documentation of a function or method.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuncDoc {
+    pub name: String,
+    pub signature: Signature,
+    /// The arguments, if the function declares them with a leading `with`.
+    pub args: Vec<String>,
+    pub doc: Vec<String>,
+}
+
+impl FuncDoc {
+    fn new(name: &str, signature: &Signature, body: &Words, doc: Vec<String>) -> Self {
+        FuncDoc {
+            name: name.to_owned(),
+            signature: signature.clone(),
+            args: match body.words.first() {
+                Some(Word::Key(Keyword::With(args))) => {
+                    args.iter().map(ToString::to_string).collect()
+                }
+                _ => Vec::new(),
+            },
+            doc,
+        }
+    }
+
+    /// The signature as it would be written in SPL, like `read { mega [int] | with buf this ; }`.
+    pub fn declaration(&self) -> String {
+        let mut output = format!("{} {{ ", self.name);
+        if self.signature.ret_count() != 0 {
+            output += &self.signature.to_string();
+            output += " ";
+        }
+        output += "|";
+        if !self.args.is_empty() {
+            output += " with ";
+            output += &self.args.join(" ");
+            output += " ;";
+        }
+        output += " }";
+        output
+    }
+}
+
+/// The documentation of a construct.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstructDoc {
+    pub name: String,
+    pub is_namespace: bool,
+    pub is_interface: bool,
+    pub is_enum: bool,
+    pub doc: Vec<String>,
+    pub fields: Vec<(String, Vec<String>)>,
+    /// The variants of an enum, like `Circle(r: mega)`.
+    pub variants: Vec<(String, Vec<String>)>,
+    /// The methods required by an interface.
+    pub requires: Vec<(String, Vec<String>)>,
+    pub methods: Vec<FuncDoc>,
+    /// The types included into this one.
+    pub parents: Vec<String>,
+}
+
+impl ConstructDoc {
+    /// The keyword declaring this construct.
+    pub fn kind(&self) -> &'static str {
+        if self.is_namespace {
+            "namespace"
+        } else if self.is_interface {
+            "interface"
+        } else if self.is_enum {
+            "enum"
+        } else {
+            "construct"
+        }
+    }
+}
+
+/// The documentation of a module (a file).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Module {
+    pub name: String,
+    pub doc: Vec<String>,
+    pub functions: Vec<FuncDoc>,
+    pub constructs: Vec<ConstructDoc>,
+    /// Every `include <parent> in <child>`, including those into types that aren't constructs.
+    pub includes: Vec<(String, String)>,
+}
+
+fn unescape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some('t') => output.push('\t'),
+            Some(c) => output.push(c),
+            None => (),
+        }
+    }
+    output
+}
+
+/// The lines of text in a comment token, without the comment syntax.
+fn comment_lines(text: &str) -> Vec<String> {
+    if let Some(text) = text.strip_prefix("/*") {
+        let text = text.strip_suffix("*/").unwrap_or(text);
+        let lines: Vec<_> = text
+            .lines()
+            .map(|x| {
+                let x = x.trim();
+                x.strip_prefix("* ")
+                    .unwrap_or(x.strip_prefix('*').unwrap_or(x))
+            })
+            .collect();
+        // `/*` and `*/` are often on lines of their own.
+        let start = lines
+            .iter()
+            .position(|x| !x.is_empty())
+            .unwrap_or(lines.len());
+        let end = lines
+            .iter()
+            .rposition(|x| !x.is_empty())
+            .map_or(start, |x| x + 1);
+        return lines[start..end].iter().map(|x| x.to_string()).collect();
+    }
+    if let Some(text) = text.strip_prefix("//").or_else(|| text.strip_prefix('#')) {
+        return vec![text.strip_prefix(' ').unwrap_or(text).trim_end().to_owned()];
+    }
+    vec![unescape(&text[1..text.len() - 2])]
+}
+
+/// Collects the comments of a module, returning the module's own documentation and that of its
+/// definitions. Definitions are keyed as `func <name>`, `construct <name>`, `interface <name>`,
+/// `enum <name>`, `field <type>.<name>` (also used for required methods and variants) and
+/// `method <type>:<name>`.
+fn comments(source: &str) -> (Vec<String>, HashMap<String, Vec<String>>) {
+    let mut module = Vec::new();
+    let mut docs = HashMap::new();
+    let mut pending: Vec<String> = Vec::new();
+    let mut pending_end = None;
+    let mut seen_code = false;
+    let mut depth = 0;
+    // the construct whose body is being read: its name, the depth of its body, and whether the
+    // fields are over.
+    let mut construct: Option<(String, Option<usize>, bool)> = None;
+    let mut expect_name: Option<(&str, Vec<String>)> = None;
+    let mut code_line = None;
+    // words of a field declaration that aren't its name: its type and default.
+    let mut skip = 0;
+    // how deeply nested in a default block, `= { <words> }`, the words being skipped are.
+    let mut default_depth = 0;
+    // whether the fields of an enum variant, `Variant(<field> <...>)`, are being read.
+    let mut in_variant = false;
+
+    for token in tokenize(source) {
+        if token.kind == TokenKind::Comment {
+            // comments after code on the same line are about that code.
+            if code_line == Some(token.line) {
+                continue;
+            }
+            if pending_end.is_some_and(|x| token.line > x + 1) {
+                if !seen_code {
+                    if !module.is_empty() {
+                        module.push(String::new());
+                    }
+                    module.append(&mut pending);
+                }
+                pending.clear();
+            }
+            pending.extend(comment_lines(&token.text));
+            pending_end = Some(token.end_line());
+            continue;
+        }
+        code_line = Some(token.end_line());
+        let doc = if pending_end.is_some_and(|x| token.line <= x + 1) {
+            pending_end = None;
+            std::mem::take(&mut pending)
+        } else {
+            if !seen_code && !pending.is_empty() {
+                if !module.is_empty() {
+                    module.push(String::new());
+                }
+                module.append(&mut pending);
+            }
+            pending.clear();
+            Vec::new()
+        };
+        seen_code = true;
+        if default_depth > 0 {
+            match token.text.as_str() {
+                "{" | "<{" => default_depth += 1,
+                "}" => default_depth -= 1,
+                _ => (),
+            }
+            continue;
+        }
+        if skip > 0 {
+            skip -= 1;
+            if token.text == "{" {
+                default_depth = 1;
+            }
+            continue;
+        }
+        if token.kind != TokenKind::Word {
+            continue;
+        }
+        if in_variant {
+            in_variant = !token.text.contains(')');
+            continue;
+        }
+
+        if let Some((kind, doc)) = expect_name.take() {
+            if kind == "construct" || kind == "interface" || kind == "enum" {
+                construct = Some((token.text.clone(), None, false));
+            }
+            docs.insert(format!("{kind} {}", token.text), doc);
+            continue;
+        }
+        match (token.text.as_str(), &mut construct) {
+            ("{" | "<{", construct) => {
+                depth += 1;
+                if let Some((_, body @ None, _)) = construct {
+                    *body = Some(depth);
+                }
+            }
+            ("}", construct) => {
+                if matches!(construct, Some((_, Some(body), _)) if *body == depth) {
+                    *construct = None;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            (";", Some((_, Some(body), methods))) if *body == depth => *methods = true,
+            ("=", Some((_, Some(body), false))) if *body == depth => skip = 1,
+            // a method called `construct` is not a keyword.
+            (name, Some((construct, Some(body), methods))) if *body == depth => {
+                let kind = if *methods { "method" } else { "field" };
+                let sep = if *methods { ":" } else { "." };
+                let name = match name.split_once('(') {
+                    Some((name, fields)) if !*methods => {
+                        in_variant = !fields.contains(')');
+                        name
+                    }
+                    _ => name,
+                };
+                let name = match name.strip_suffix(':') {
+                    Some(name) if !*methods => {
+                        skip = 1;
+                        name
+                    }
+                    _ => name,
+                };
+                docs.insert(format!("{kind} {construct}{sep}{name}"), doc);
+            }
+            ("func", _) => expect_name = Some(("func", doc)),
+            ("construct", _) => expect_name = Some(("construct", doc)),
+            ("interface", _) => expect_name = Some(("interface", doc)),
+            ("enum", _) => expect_name = Some(("enum", doc)),
+            _ => (),
+        }
+    }
+    if !seen_code {
+        module.append(&mut pending);
+    }
+    (module, docs)
+}
+
+/// Reads the documentation of a module from its source.
+///
+/// ```
+/// let source = "\"Adds one.\";\nfunc ++ { mega | 1 + }";
+/// let module = spl::docgen::document("math", source).unwrap();
+/// assert_eq!(module.functions[0].doc, vec!["Adds one.".to_owned()]);
+/// ```
+pub fn document(name: &str, source: &str) -> Result<Module, LexerError> {
+    let words = lex(source.to_owned())?;
+    let (doc, mut docs) = comments(source);
+    let mut take = |key: String| docs.remove(&key).unwrap_or_default();
+    let mut module = Module {
+        name: name.to_owned(),
+        doc,
+        functions: Vec::new(),
+        constructs: Vec::new(),
+        includes: Vec::new(),
+    };
+    for word in words.words {
+        match word {
+            Word::Key(Keyword::Func(name, signature, body)) => {
+                let doc = take(format!("func {name}"));
+                module
+                    .functions
+                    .push(FuncDoc::new(&name, &signature, &body, doc));
+            }
+            Word::Key(Keyword::Construct(name, fields, methods, is_namespace)) => {
+                module.constructs.push(ConstructDoc {
+                    doc: take(format!("construct {name}")),
+                    fields: fields
+                        .into_iter()
+                        .map(|field| {
+                            let doc = take(format!("field {name}.{}", field.name));
+                            (field.to_string(), doc)
+                        })
+                        .collect(),
+                    methods: methods
+                        .into_iter()
+                        // constructors added by the lexer have no body.
+                        .filter(|(_, (_, body))| !body.words.is_empty())
+                        .map(|(method, (signature, body))| {
+                            let doc = take(format!("method {name}:{method}"));
+                            FuncDoc::new(&method, &signature, &body, doc)
+                        })
+                        .collect(),
+                    requires: Vec::new(),
+                    variants: Vec::new(),
+                    parents: Vec::new(),
+                    name,
+                    is_namespace,
+                    is_interface: false,
+                    is_enum: false,
+                });
+            }
+            Word::Key(Keyword::Interface(name, requires, methods)) => {
+                module.constructs.push(ConstructDoc {
+                    doc: take(format!("interface {name}")),
+                    fields: Vec::new(),
+                    // the comments in front of required methods are found like those of fields.
+                    requires: requires
+                        .into_iter()
+                        .map(|(method, signature)| {
+                            let doc = take(format!("field {name}.{method}"));
+                            let declaration = match signature {
+                                Some(signature) => FuncDoc::new(
+                                    &method,
+                                    &signature,
+                                    &Words::new(Vec::new()),
+                                    Vec::new(),
+                                )
+                                .declaration(),
+                                None => method,
+                            };
+                            (declaration, doc)
+                        })
+                        .collect(),
+                    methods: methods
+                        .into_iter()
+                        .map(|(method, (signature, body))| {
+                            let doc = take(format!("method {name}:{method}"));
+                            FuncDoc::new(&method, &signature, &body, doc)
+                        })
+                        .collect(),
+                    variants: Vec::new(),
+                    parents: Vec::new(),
+                    name,
+                    is_namespace: false,
+                    is_interface: true,
+                    is_enum: false,
+                });
+            }
+            Word::Key(Keyword::Enum(name, variants, methods)) => {
+                module.constructs.push(ConstructDoc {
+                    doc: take(format!("enum {name}")),
+                    fields: Vec::new(),
+                    requires: Vec::new(),
+                    // the comments in front of variants are found like those of fields.
+                    variants: variants
+                        .into_iter()
+                        .map(|(variant, fields)| {
+                            let doc = take(format!("field {name}.{variant}"));
+                            if fields.is_empty() {
+                                return (variant, doc);
+                            }
+                            let fields: Vec<_> = fields.iter().map(ToString::to_string).collect();
+                            (format!("{variant}({})", fields.join(" ")), doc)
+                        })
+                        .collect(),
+                    methods: methods
+                        .into_iter()
+                        .map(|(method, (signature, body))| {
+                            let doc = take(format!("method {name}:{method}"));
+                            FuncDoc::new(&method, &signature, &body, doc)
+                        })
+                        .collect(),
+                    parents: Vec::new(),
+                    name,
+                    is_namespace: false,
+                    is_interface: false,
+                    is_enum: true,
+                });
+            }
+            Word::Key(Keyword::Include(parent, child)) => {
+                if let Some(c) = module.constructs.iter_mut().find(|x| x.name == child) {
+                    c.parents.push(parent.clone());
+                }
+                module.includes.push((parent, child));
+            }
+            _ => (),
+        }
+    }
+    Ok(module)
+}
+
+/// Knows which module each construct is documented in, and which constructs include it, for
+/// links between modules.
+pub struct Index {
+    modules: HashMap<String, String>,
+    children: HashMap<String, Vec<String>>,
+}
+
+impl Index {
+    pub fn new(modules: &[Module]) -> Self {
+        let mut index = Index {
+            modules: HashMap::new(),
+            children: HashMap::new(),
+        };
+        for module in modules {
+            for construct in &module.constructs {
+                index
+                    .modules
+                    .insert(construct.name.clone(), module.name.clone());
+            }
+            for (parent, child) in &module.includes {
+                index
+                    .children
+                    .entry(parent.clone())
+                    .or_default()
+                    .push(child.clone());
+            }
+        }
+        index
+    }
+
+    /// The link to the documentation of a construct, if it is documented.
+    fn link(&self, construct: &str, extension: &str) -> Option<String> {
+        self.modules
+            .get(construct)
+            .map(|module| format!("{module}.{extension}#construct-{construct}"))
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Module {
+    /// Renders the module as Markdown, linking constructs to `<module>.md`.
+    pub fn to_markdown(&self, index: &Index) -> String {
+        let paragraphs = |doc: &[String]| doc.join("\n") + "\n\n";
+        let link = |name: &str| match index.link(name, "md") {
+            Some(link) => format!("[`{name}`]({link})"),
+            None => format!("`{name}`"),
+        };
+        let mut output = format!("# Module `{}`\n\n", self.name);
+        if !self.doc.is_empty() {
+            output += &paragraphs(&self.doc);
+        }
+        if !self.constructs.is_empty() {
+            output += "## Constructs\n\n";
+        }
+        for construct in &self.constructs {
+            let kind = construct.kind();
+            output += &format!(
+                "### <a id=\"construct-{0}\"></a>{kind} `{0}`\n\n",
+                construct.name
+            );
+            if !construct.doc.is_empty() {
+                output += &paragraphs(&construct.doc);
+            }
+            if !construct.parents.is_empty() {
+                let parents: Vec<_> = construct.parents.iter().map(|x| link(x)).collect();
+                output += &format!("Includes: {}\n\n", parents.join(", "));
+            }
+            if let Some(children) = index.children.get(&construct.name) {
+                let children: Vec<_> = children.iter().map(|x| link(x)).collect();
+                output += &format!("Included by: {}\n\n", children.join(", "));
+            }
+            if !construct.fields.is_empty() {
+                output += "Fields:\n\n";
+                for (field, doc) in &construct.fields {
+                    output += &format!("- `{field}`");
+                    if !doc.is_empty() {
+                        output += ": ";
+                        output += &doc.join(" ");
+                    }
+                    output += "\n";
+                }
+                output += "\n";
+            }
+            if !construct.variants.is_empty() {
+                output += "Variants:\n\n";
+                for (variant, doc) in &construct.variants {
+                    output += &format!("- `{variant}`");
+                    if !doc.is_empty() {
+                        output += ": ";
+                        output += &doc.join(" ");
+                    }
+                    output += "\n";
+                }
+                output += "\n";
+            }
+            if !construct.requires.is_empty() {
+                output += "Requires:\n\n";
+                for (method, doc) in &construct.requires {
+                    output += &format!("- `{method}`");
+                    if !doc.is_empty() {
+                        output += ": ";
+                        output += &doc.join(" ");
+                    }
+                    output += "\n";
+                }
+                output += "\n";
+            }
+            if !construct.methods.is_empty() {
+                output += "Methods:\n\n";
+                for method in &construct.methods {
+                    output += &format!("- `{}`", method.declaration());
+                    if !method.doc.is_empty() {
+                        output += ": ";
+                        output += &method.doc.join(" ");
+                    }
+                    output += "\n";
+                }
+                output += "\n";
+            }
+        }
+        if !self.functions.is_empty() {
+            output += "## Functions\n\n";
+        }
+        for function in &self.functions {
+            output += &format!(
+                "### <a id=\"func-{}\"></a>`func {}`\n\n",
+                function.name,
+                function.declaration()
+            );
+            if !function.doc.is_empty() {
+                output += &paragraphs(&function.doc);
+            }
+        }
+        output.truncate(output.trim_end().len());
+        output + "\n"
+    }
+
+    /// Renders the module as a standalone HTML page, linking constructs to `<module>.html`.
+    pub fn to_html(&self, index: &Index) -> String {
+        let paragraphs = |doc: &[String]| {
+            if doc.is_empty() {
+                return String::new();
+            }
+            doc.join("\n")
+                .split("\n\n")
+                .map(|x| format!("<p>{}</p>\n", escape_html(x)))
+                .collect::<String>()
+        };
+        let link = |name: &str| match index.link(name, "html") {
+            Some(link) => format!(
+                "<a href=\"{}\"><code>{}</code></a>",
+                escape_html(&link),
+                escape_html(name)
+            ),
+            None => format!("<code>{}</code>", escape_html(name)),
+        };
+        let item = |code: &str, doc: &[String]| {
+            let mut output = format!("<li><code>{}</code>", escape_html(code));
+            if !doc.is_empty() {
+                output += ": ";
+                output += &escape_html(&doc.join(" "));
+            }
+            output + "</li>\n"
+        };
+        let name = escape_html(&self.name);
+        let mut output = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{name}</title>\n</head>\n<body>\n<h1>Module <code>{name}</code></h1>\n"
+        );
+        output += &paragraphs(&self.doc);
+        if !self.constructs.is_empty() {
+            output += "<h2>Constructs</h2>\n";
+        }
+        for construct in &self.constructs {
+            let kind = construct.kind();
+            output += &format!(
+                "<h3 id=\"construct-{0}\">{kind} <code>{0}</code></h3>\n",
+                escape_html(&construct.name)
+            );
+            output += &paragraphs(&construct.doc);
+            if !construct.parents.is_empty() {
+                let parents: Vec<_> = construct.parents.iter().map(|x| link(x)).collect();
+                output += &format!("<p>Includes: {}</p>\n", parents.join(", "));
+            }
+            if let Some(children) = index.children.get(&construct.name) {
+                let children: Vec<_> = children.iter().map(|x| link(x)).collect();
+                output += &format!("<p>Included by: {}</p>\n", children.join(", "));
+            }
+            if !construct.fields.is_empty() {
+                output += "<p>Fields:</p>\n<ul>\n";
+                for (field, doc) in &construct.fields {
+                    output += &item(field, doc);
+                }
+                output += "</ul>\n";
+            }
+            if !construct.variants.is_empty() {
+                output += "<p>Variants:</p>\n<ul>\n";
+                for (variant, doc) in &construct.variants {
+                    output += &item(variant, doc);
+                }
+                output += "</ul>\n";
+            }
+            if !construct.requires.is_empty() {
+                output += "<p>Requires:</p>\n<ul>\n";
+                for (method, doc) in &construct.requires {
+                    output += &item(method, doc);
+                }
+                output += "</ul>\n";
+            }
+            if !construct.methods.is_empty() {
+                output += "<p>Methods:</p>\n<ul>\n";
+                for method in &construct.methods {
+                    output += &item(&method.declaration(), &method.doc);
+                }
+                output += "</ul>\n";
+            }
+        }
+        if !self.functions.is_empty() {
+            output += "<h2>Functions</h2>\n";
+        }
+        for function in &self.functions {
+            output += &format!(
+                "<h3 id=\"func-{}\"><code>func {}</code></h3>\n",
+                escape_html(&function.name),
+                escape_html(&function.declaration())
+            );
+            output += &paragraphs(&function.doc);
+        }
+        output + "</body>\n</html>\n"
+    }
+}
@@ -81,7 +81,7 @@ pub fn dyn_def_field(stack: &mut Stack) -> OError {
     runtime(|rt| rt.get_type_by_name(&s))
         .ok_or_else(|| stack.error(ErrorKind::TypeNotFound(s)))?
         .lock()
-        .add_property(name, stack.get_frame())?;
+        .add_property(name, TypeSpec::Any, stack.get_frame())?;
     Ok(())
 }
 
@@ -152,6 +152,21 @@ pub fn dyn_if(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+pub fn dyn_if_else(stack: &mut Stack) -> OError {
+    let (Value::Func(els), Value::Func(blk)) = (
+        stack.pop().lock_ro().native.clone(),
+        stack.pop().lock_ro().native.clone(),
+    ) else {
+        return stack.err(ErrorKind::InvalidCall("dyn-if-else".to_owned()));
+    };
+    if stack.pop().lock_ro().is_truthy() {
+        blk.to_call.call(stack)?;
+    } else {
+        els.to_call.call(stack)?;
+    }
+    Ok(())
+}
+
 pub fn dyn_call(stack: &mut Stack) -> OError {
     let Value::Str(mut s) = stack.pop().lock_ro().native.clone() else {
         return stack.err(ErrorKind::InvalidCall("dyn-call".to_owned()))
@@ -214,6 +229,7 @@ pub fn dyn_read(stack: &mut Stack) -> OError {
                 lexer::lex(s).map_err(|x| stack.error(ErrorKind::LexError(format!("{x:?}"))))?,
             ),
             run_as_base: false,
+            signature: None,
             origin: stack.get_frame(),
             fname: None,
             name: "(dyn-read)".to_owned(),
@@ -240,6 +256,7 @@ pub fn dyn_readf(stack: &mut Stack) -> OError {
                 lexer::lex(s).map_err(|x| stack.error(ErrorKind::LexError(format!("{x:?}"))))?,
             ),
             run_as_base: true,
+            signature: None,
             origin: stack.get_frame(),
             fname: Some(n),
             name: "root".to_owned(),
@@ -259,6 +276,7 @@ pub fn dyn_sasm(stack: &mut Stack) -> OError {
             fname: None,
             name: "(dyn-read)".to_owned(),
             run_as_base: false,
+            signature: None,
         }))
         .spl(),
     );
@@ -276,6 +294,7 @@ pub fn dyn_sasmf(stack: &mut Stack) -> OError {
             fname: Some(n),
             name: "root".to_owned(),
             run_as_base: true,
+            signature: None,
         }))
         .spl(),
     );
@@ -287,8 +306,13 @@ pub fn dyn_catch(stack: &mut Stack) -> OError {
     require_on_stack!(blk, Func, stack, "dyn-catch");
     require_on_stack!(types, Array, stack, "dyn-catch");
     if let Err(e) = blk.to_call.call(stack) {
-        if types.is_empty() || types.contains(&e.kind.to_string().spl()) {
-            stack.push(e.spl());
+        let is = |x: &AMObject| match &x.lock_ro().native {
+            Value::Str(x) => e.kind.is(x),
+            _ => false,
+        };
+        if types.is_empty() || types.iter().any(is) {
+            let e = e.into_object(stack)?;
+            stack.push(e);
             ctch.to_call.call(stack)
         } else {
             Err(e)
@@ -314,7 +338,7 @@ pub(crate) fn wrap(f: fn(&mut Stack) -> OError) -> impl Fn(&mut Stack) -> OError
 
 pub fn register(r: &mut Stack, o: Arc<Frame>) {
     type Fn = fn(&mut Stack) -> OError;
-    let fns: [(&str, Fn, u32); 19] = [
+    let fns: [(&str, Fn, u32); 20] = [
         ("dyn-__dump", dyn_dump, 0),
         ("dyn-def", dyn_def, 0),
         ("dyn-func", dyn_func, 0),
@@ -325,6 +349,7 @@ pub fn register(r: &mut Stack, o: Arc<Frame>) {
         ("dyn-include", dyn_include, 0),
         ("dyn-while", dyn_while, 0),
         ("dyn-if", dyn_if, 0),
+        ("dyn-if-else", dyn_if_else, 0),
         ("dyn-call", dyn_call, 0),
         ("dyn-objcall", dyn_objcall, 0),
         ("dyn-all-types", dyn_all_types, 1),
@@ -342,6 +367,7 @@ pub fn register(r: &mut Stack, o: Arc<Frame>) {
                 ret_count: f.2,
                 to_call: FuncImpl::NativeDyn(Arc::new(Box::new(wrap(f.1)))),
                 run_as_base: false,
+                signature: None,
                 origin: o.clone(),
                 fname: None,
                 name: f.0.to_owned(),
@@ -0,0 +1,168 @@
+//! The canonical SPL formatter, used by `spl fmt`.
+//!
+//! The formatter works on [Token]s, so it only ever changes the whitespace between them. Comments
+//! and raw blocks (`!{ ... }`) are kept exactly as written. The rules are:
+//! - every block (`{ }`, `<{ }`, `[ ]` and `( )`) indents its contents by one level, no matter how
+//!   many blocks are opened on the same line.
+//! - lines starting with a closing bracket go back to the indentation of the line that opened it.
+//! - lines that were indented further than the line before them without being in a new block
+//!   (like `:method` chains) are continuations and get one extra level.
+//! - words on a line are separated by exactly one space.
+//! - there is at most one empty line in a row, and the file ends with a single line break.
+
+use crate::lexer::{tokenize, Token, TokenKind};
+
+/// One level of indentation.
+pub const INDENT: &str = "    ";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bracket {
+    Open(char),
+    Close(char),
+}
+
+fn brackets(token: &Token) -> Vec<Bracket> {
+    if token.kind != TokenKind::Word {
+        return Vec::new();
+    }
+    match token.text.as_str() {
+        "{" | "<{" => vec![Bracket::Open('{')],
+        "[" => vec![Bracket::Open('[')],
+        "}" => vec![Bracket::Close('{')],
+        "]" => vec![Bracket::Close('[')],
+        x => x
+            .chars()
+            .filter_map(|c| match c {
+                '(' => Some(Bracket::Open('(')),
+                ')' => Some(Bracket::Close('(')),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+/// Closes a bracket, returning the indentation level of the line it was opened on. `[` is just a
+/// function, so it doesn't have to be closed and is skipped when closing anything else.
+fn close(open: &mut Vec<(char, usize)>, kind: char) -> Option<usize> {
+    let idx = open.iter().rposition(|x| x.0 == kind)?;
+    if open[idx + 1..].iter().any(|x| x.0 != '[') {
+        return None;
+    }
+    let level = open[idx].1;
+    open.truncate(idx);
+    Some(level)
+}
+
+fn is_closer(token: &Token) -> bool {
+    token.kind == TokenKind::Word
+        && !token.text.is_empty()
+        && token.text.chars().all(|c| c == '}' || c == ']' || c == ')')
+}
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { INDENT.len() } else { 1 })
+        .sum()
+}
+
+struct PrevLine {
+    end_line: usize,
+    depth: usize,
+    balanced: bool,
+    base_indent: usize,
+}
+
+/// Formats SPL source.
+///
+/// ```
+/// let code = "func main { mega | with args ;\n\t0\n  }";
+/// assert_eq!(spl::formatter::format(code), "func main { mega | with args ;\n    0\n}\n");
+/// ```
+pub fn format(input: &str) -> String {
+    let tokens = tokenize(input);
+    let src_lines: Vec<_> = input.split('\n').collect();
+    let mut output = String::new();
+    // each currently open bracket and the indentation level of the line it was opened on.
+    let mut open: Vec<(char, usize)> = Vec::new();
+    let mut prev: Option<PrevLine> = None;
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let start = i;
+        let mut end_line = tokens[i].end_line();
+        i += 1;
+        while i < tokens.len() && tokens[i].line <= end_line {
+            end_line = tokens[i].end_line();
+            i += 1;
+        }
+        let line = &tokens[start..i];
+
+        if let Some(ref prev) = prev {
+            if line[0].line > prev.end_line + 1 {
+                output += "\n";
+            }
+        }
+
+        let mut indent = None;
+        let mut leading = 0;
+        while leading < line.len() && is_closer(&line[leading]) {
+            for bracket in brackets(&line[leading]) {
+                if let Bracket::Close(kind) = bracket {
+                    indent = close(&mut open, kind).or(indent);
+                }
+            }
+            leading += 1;
+        }
+        let closed = leading != 0;
+        let depth = open.len();
+        let mut indent = indent.unwrap_or_else(|| open.last().map(|x| x.1 + 1).unwrap_or(0));
+
+        let src_indent = indent_width(src_lines[line[0].line]);
+        let base_indent = match prev {
+            Some(ref prev)
+                if !closed
+                    && prev.balanced
+                    && prev.depth == depth
+                    && src_indent > prev.base_indent =>
+            {
+                indent += 1;
+                prev.base_indent
+            }
+            _ => src_indent,
+        };
+
+        for (j, token) in line.iter().enumerate().skip(leading) {
+            // function names aren't brackets, even if they are `[` or `]`.
+            if j > 0 && line[j - 1].text == "func" {
+                continue;
+            }
+            for bracket in brackets(token) {
+                match bracket {
+                    Bracket::Open(kind) => open.push((kind, indent)),
+                    Bracket::Close(kind) => {
+                        close(&mut open, kind);
+                    }
+                }
+            }
+        }
+
+        for _ in 0..indent {
+            output += INDENT;
+        }
+        output += &line
+            .iter()
+            .map(|x| x.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        output += "\n";
+
+        prev = Some(PrevLine {
+            end_line,
+            depth: open.len(),
+            balanced: open.len() == depth,
+            base_indent,
+        });
+    }
+    output
+}
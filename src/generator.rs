@@ -0,0 +1,213 @@
+//! Generators: functions that hand out values one at a time using `yield`.
+//!
+//! The body of a generator runs on its own thread, sharing the runtime of the thread that created
+//! it. The two threads hand control to each other through channels, so only one of them ever runs
+//! at a time: `yield` gives a value to the caller of the generator and waits until the next one is
+//! requested.
+//!
+//! The thread is started when the first value is requested and ends with the body, or without
+//! running the rest of it when the generator is dropped while waiting in `yield`. Stopping a thread
+//! waits for it to finish unwinding, so it never runs next to the thread that stopped it. A
+//! generator that is still referred to by its own body, for example through a variable of the
+//! frame the body was made in, is never dropped, like any other cycle of references. Its thread is
+//! stopped when the runtime is reset.
+//!
+//! Functions and objects aren't [Send], as native functions and streams may hold values that
+//! aren't, so the body and the values handed back and forth are moved to and from the thread
+//! through [Start] and [Yielded]. This is sound because the runtime and everything in it is only
+//! ever used by the one thread that is running: every handoff is a send on a channel that the
+//! other thread is waiting to receive on, which orders everything one thread did before it with
+//! everything the other does after it.
+
+use std::{
+    cell::RefCell,
+    mem, panic,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Weak,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{mutex::Mut, runtime::*, *};
+
+/// What the body of a generator hands back when it stops running: the yielded value, None when
+/// the body returned, or the error it threw.
+type Handoff = Result<Option<AMObject>, Error>;
+
+thread_local! {
+    static GENERATOR: RefCell<Option<(Sender<Yielded>, Receiver<bool>)>> =
+        const { RefCell::new(None) };
+}
+
+/// What a generator thread needs to run its body.
+struct Start(Arc<Mut<Runtime>>, AFunc, Sender<Yielded>);
+
+// SAFETY: The runtime and body are only used by the generator thread after it received the first
+// request for a value, while the requesting thread waits for the value.
+unsafe impl Send for Start {}
+
+/// A value handed from a generator thread to the thread resuming it.
+struct Yielded(Handoff);
+
+// SAFETY: The generator thread doesn't touch the value after sending it, as it then waits to be
+// resumed, and the resuming thread only runs while the generator thread waits.
+unsafe impl Send for Yielded {}
+
+/// Unwinds the thread of a generator that was dropped while suspended, without running any more
+/// SPL code.
+struct Dropped;
+
+/// The thread running the body of a generator. Dropping it stops the thread if it is waiting in
+/// `yield`, and waits until it has ended.
+pub struct GeneratorThread {
+    /// Sending true resumes the thread, sending false stops it.
+    resume: Sender<bool>,
+    handle: Mut<Option<JoinHandle<()>>>,
+}
+
+impl GeneratorThread {
+    /// Stops the thread if it is waiting in `yield`, and waits until it has ended. Returns what
+    /// the thread panicked with, if it did so other than by being stopped.
+    pub fn stop(&self) -> Option<Box<dyn std::any::Any + Send>> {
+        let _ = self.resume.send(false);
+        let handle = self.handle.lock().take()?;
+        handle.join().err().filter(|x| !x.is::<Dropped>())
+    }
+}
+
+impl Drop for GeneratorThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Where a generator is.
+enum State {
+    /// Not asked for a value yet. Holds the body.
+    New(AFunc),
+    /// Waiting in `yield`.
+    Suspended(Arc<GeneratorThread>, Receiver<Yielded>),
+    Done,
+}
+
+/// Starts the thread running a generator body, which waits until it is resumed.
+fn spawn(body: AFunc) -> (Arc<GeneratorThread>, Receiver<Yielded>) {
+    let (resume_tx, resume_rx) = channel::<bool>();
+    let (yield_tx, yield_rx) = channel::<Yielded>();
+    let start = Start(Runtime::get(), body, yield_tx);
+    let handle = thread::Builder::new()
+        .name("generator".to_owned())
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || {
+            let start = start;
+            let Start(rt, body, yield_tx) = start;
+            rt.set();
+            if resume_rx.recv() != Ok(true) {
+                return;
+            }
+            GENERATOR.with(|x| *x.borrow_mut() = Some((yield_tx.clone(), resume_rx)));
+            let mut stack = Stack::new();
+            let result = stack.call(&body).map(|_| None);
+            let _ = yield_tx.send(Yielded(result));
+        })
+        .expect("unable to start generator thread");
+    let thread = Arc::new(GeneratorThread {
+        resume: resume_tx,
+        handle: Mut::new(Some(handle)),
+    });
+    runtime_mut(|mut rt| rt.register_generator(Arc::downgrade(&thread)));
+    (thread, yield_rx)
+}
+
+/// Makes the function that resumes a generator running the body.
+fn start(body: AFunc) -> AFunc {
+    let state = RefCell::new(State::New(body));
+    AFunc::new(Func {
+        ret_count: 1,
+        to_call: FuncImpl::NativeDyn(Arc::new(Box::new(move |stack| {
+            let current = mem::replace(&mut *state.borrow_mut(), State::Done);
+            let (thread, yield_rx) = match current {
+                State::New(body) => spawn(body),
+                State::Suspended(thread, yield_rx) => (thread, yield_rx),
+                State::Done => {
+                    stack.push(Value::Null.spl());
+                    return Ok(());
+                }
+            };
+            let _ = thread.resume.send(true);
+            match yield_rx.recv() {
+                Ok(Yielded(Ok(Some(value)))) => {
+                    *state.borrow_mut() = State::Suspended(thread, yield_rx);
+                    stack.push(value);
+                }
+                Ok(Yielded(Err(error))) => return Err(error),
+                Ok(Yielded(Ok(None))) => stack.push(Value::Null.spl()),
+                // the thread panicked, so the panic is passed on to the thread resuming it.
+                Err(_) => {
+                    if let Some(payload) = thread.stop() {
+                        panic::resume_unwind(payload);
+                    }
+                    stack.push(Value::Null.spl());
+                }
+            }
+            Ok(())
+        }))),
+        origin: Arc::new(Frame::dummy()),
+        run_as_base: false,
+        signature: None,
+        fname: Some("RUNTIME".to_owned()),
+        name: "resume-generator".to_owned(),
+    })
+}
+
+/// Stops the threads of all generators in the runtime that are waiting to be resumed, and waits
+/// until they have ended.
+pub fn stop_all(generators: Vec<Weak<GeneratorThread>>) {
+    for thread in generators.iter().filter_map(Weak::upgrade) {
+        thread.stop();
+    }
+}
+
+pub fn new_generator(stack: &mut Stack) -> OError {
+    require_on_stack!(body, Func, stack, "new-generator");
+    stack.push(Value::Func(start(body)).spl());
+    Ok(())
+}
+
+pub fn yield_value(stack: &mut Stack) -> OError {
+    let value = stack.pop();
+    let resumed = GENERATOR.with(|x| {
+        let x = x.borrow();
+        let (yield_tx, resume_rx) = x.as_ref()?;
+        let _ = yield_tx.send(Yielded(Ok(Some(value))));
+        Some(resume_rx.recv() == Ok(true))
+    });
+    match resumed {
+        None => stack.err(ErrorKind::InvalidCall("yield".to_owned())),
+        Some(false) => panic::resume_unwind(Box::new(Dropped)),
+        Some(true) => Ok(()),
+    }
+}
+
+pub fn register(r: &mut Stack, o: Arc<Frame>) {
+    type Fn = fn(&mut Stack) -> OError;
+    let fns: [(&str, Fn, u32); 2] = [
+        ("new-generator", new_generator, 1),
+        ("yield", yield_value, 0),
+    ];
+    for f in fns {
+        r.define_func(
+            f.0.to_owned(),
+            AFunc::new(Func {
+                ret_count: f.2,
+                to_call: FuncImpl::Native(f.1),
+                run_as_base: false,
+                signature: None,
+                origin: o.clone(),
+                fname: None,
+                name: f.0.to_owned(),
+            }),
+        );
+    }
+}
@@ -1,6 +1,9 @@
-use std::{mem, sync::Arc};
+use std::{iter::Peekable, mem, sync::Arc, vec::IntoIter};
 
-use crate::runtime::*;
+use crate::{
+    bignum::{BigInt, Decimal},
+    runtime::*,
+};
 use readformat::*;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -9,8 +12,15 @@ pub enum LexerError {
     WrongFunctionDeclaration,
     InvalidInclude,
     InvalidConstructBlock,
+    InvalidInterfaceBlock,
+    InvalidEnumBlock,
+    InvalidMatchBlock,
+    InvalidForLoop,
+    InvalidWith,
     InvalidNumber(String),
     ArgsWithoutCall,
+    /// A `const` in a loop, which would be defined again in the same frame on the next run.
+    ConstInLoop(String),
 }
 
 pub fn lex(input: String) -> Result<Words, LexerError> {
@@ -18,7 +28,178 @@ pub fn lex(input: String) -> Result<Words, LexerError> {
     Ok(read_block(&str_words[..], false)?.1)
 }
 
-fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, usize), LexerError> {
+/// Reads the methods of a construct or interface, if `i` points to the `;` in front of them. `i` is
+/// moved to the closing brace.
+fn read_methods(
+    str_words: &[String],
+    i: &mut usize,
+) -> Result<Vec<(String, (Signature, Words))>, LexerError> {
+    let mut methods = Vec::new();
+    if str_words[*i] != ";" {
+        return Ok(methods);
+    }
+    *i += 1;
+    while str_words[*i] != "}" {
+        let name = str_words[*i].to_owned();
+        let block = read_block(&str_words[*i + 1..], true)?;
+        *i += 1 + block.2;
+        methods.push((
+            name,
+            (block.0.ok_or(LexerError::FunctionBlockExpected)?, block.1),
+        ));
+        *i += 1;
+    }
+    Ok(methods)
+}
+
+/// Reads a value that is either a single word or a block, `{ <words> }`, returning its words and
+/// the amount of words read.
+fn read_value(str_words: &[String]) -> Result<(Words, usize), LexerError> {
+    if str_words[0] == "{" {
+        let blk = read_block(&str_words[1..], false)?;
+        return Ok((blk.1, blk.2 + 2));
+    }
+    let blk = read_block(&[str_words[0].clone(), "}".to_owned()], false)?;
+    Ok((blk.1, 1))
+}
+
+/// Reads the fields of a construct or enum variant, `<name>[: <type>] [= <word>|{ <words> }]`,
+/// until `i` points to a `;`, `}` or `)`.
+fn read_fields(str_words: &[String], i: &mut usize) -> Result<Vec<Field>, LexerError> {
+    let mut fields = Vec::new();
+    while !matches!(str_words[*i].as_str(), ";" | "}" | ")") {
+        let mut field = match str_words[*i].strip_suffix(':') {
+            Some(name) => {
+                *i += 1;
+                let mut field = Field::new(name.to_owned());
+                field.kind = TypeSpec::parse(&str_words[*i]);
+                field
+            }
+            None => Field::new(str_words[*i].to_owned()),
+        };
+        *i += 1;
+        if str_words[*i] == "=" {
+            let (default, len) = read_value(&str_words[*i + 1..])?;
+            field.default = Some(default);
+            *i += 1 + len;
+        }
+        fields.push(field);
+    }
+    Ok(fields)
+}
+
+/// Reads the else or elif blocks following an if block, if there are any. `i` must point to the
+/// end of the previous block, and is moved to the end of the last one read.
+fn read_else(str_words: &[String], i: &mut usize) -> Result<Option<Words>, LexerError> {
+    match str_words.get(*i + 1).map(String::as_str) {
+        Some("else") => {
+            let blk = read_block(&str_words[*i + 3..], false)?;
+            *i += 3 + blk.2;
+            Ok(Some(blk.1))
+        }
+        Some("elif") => {
+            let cond = read_block(&str_words[*i + 3..], false)?;
+            *i += 3 + cond.2;
+            let blk = read_block(&str_words[*i + 2..], false)?;
+            *i += 2 + blk.2;
+            let mut words = cond.1.words;
+            words.push(Word::Key(match read_else(str_words, i)? {
+                Some(els) => Keyword::IfElse(blk.1, els),
+                None => Keyword::If(blk.1),
+            }));
+            Ok(Some(Words::new(words)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Finds a `const` that runs in the frame of the block, so not in functions defined in it.
+fn find_const(words: &Words) -> Option<String> {
+    words.words.iter().find_map(|word| match word {
+        Word::Key(Keyword::Const(name, _)) => Some(name.clone()),
+        Word::Key(key) => key.blocks().into_iter().find_map(find_const),
+        _ => None,
+    })
+}
+
+/// Parses the items of a `with`, like `a [b _ ..c] {d e}`. Brackets may be written separately
+/// or attached to the names.
+pub fn parse_bindings(words: &[String]) -> Result<Vec<Binding>, LexerError> {
+    let mut tokens = Vec::new();
+    for word in words {
+        let core = word.trim_start_matches(['[', '{']);
+        tokens.extend(word[..word.len() - core.len()].chars().map(String::from));
+        let name = core.trim_end_matches([']', '}']);
+        if !name.is_empty() {
+            tokens.push(name.to_owned());
+        }
+        tokens.extend(core[name.len()..].chars().map(String::from));
+    }
+    let mut tokens = tokens.into_iter().peekable();
+    let mut bindings = Vec::new();
+    while tokens.peek().is_some() {
+        bindings.push(read_binding(&mut tokens)?);
+    }
+    Ok(bindings)
+}
+
+fn read_binding(tokens: &mut Peekable<IntoIter<String>>) -> Result<Binding, LexerError> {
+    let is_name = |x: &str| !["[", "]", "{", "}"].contains(&x) && !x.starts_with("..");
+    let token = tokens.next().ok_or(LexerError::InvalidWith)?;
+    match token.as_str() {
+        "[" => {
+            let mut items = Vec::new();
+            loop {
+                match tokens.peek().map(String::as_str) {
+                    Some("]") => {
+                        tokens.next();
+                        return Ok(Binding::Array(items, None));
+                    }
+                    Some(x) if x.len() > 2 && x.starts_with("..") => {
+                        let rest = x[2..].to_owned();
+                        tokens.next();
+                        if tokens.next().as_deref() != Some("]") {
+                            return Err(LexerError::InvalidWith);
+                        }
+                        return Ok(Binding::Array(items, Some(rest)));
+                    }
+                    Some(_) => items.push(read_binding(tokens)?),
+                    None => return Err(LexerError::InvalidWith),
+                }
+            }
+        }
+        "{" => {
+            let mut names = Vec::new();
+            loop {
+                match tokens.next() {
+                    Some(x) if x == "}" => return Ok(Binding::Fields(names)),
+                    Some(x) if is_name(&x) => names.push(x),
+                    _ => return Err(LexerError::InvalidWith),
+                }
+            }
+        }
+        x if is_name(x) => Ok(Binding::Name(token)),
+        _ => Err(LexerError::InvalidWith),
+    }
+}
+
+fn lambda(signature: Option<Signature>, words: Words) -> Result<Word, LexerError> {
+    let signature = signature.ok_or(LexerError::FunctionBlockExpected)?;
+    Ok(Word::Const(Value::Func(AFunc::new(Func {
+        ret_count: signature.ret_count(),
+        to_call: FuncImpl::SPL(words),
+        origin: Arc::new(Frame::dummy()),
+        fname: None,
+        name: "dyn".to_owned(),
+        run_as_base: false,
+        signature: Some(signature),
+    }))))
+}
+
+fn read_block(
+    str_words: &[String],
+    isfn: bool,
+) -> Result<(Option<Signature>, Words, usize), LexerError> {
     if str_words.is_empty() {
         return Ok((None, Words::new(Vec::new()), 0));
     }
@@ -26,12 +207,12 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
     let mut words = Vec::new();
     let mut i = 0;
     if str_words[0] == "{" && isfn {
-        let mut r = 0_u32;
-        while str_words[r as usize + 1] != "|" {
+        let mut r = 0;
+        while str_words[r + 1] != "|" {
             r += 1;
         }
-        i += r as usize + 2;
-        rem = Some(r);
+        i += r + 2;
+        rem = Some(Signature::parse(&str_words[1..=r]));
     }
     while i < str_words.len() {
         let word = str_words[i].to_owned();
@@ -40,6 +221,11 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
                 words.push(Word::Key(Keyword::Def(str_words[i + 1].to_owned())));
                 i += 1;
             }
+            "const" => {
+                let (value, len) = read_value(&str_words[i + 2..])?;
+                words.push(Word::Key(Keyword::Const(str_words[i + 1].to_owned(), value)));
+                i += 1 + len;
+            }
             "func" => {
                 if let Some(dat) = readf1("func\0{}\0{", str_words[i..=i + 2].join("\0").as_str()) {
                     let block = read_block(&str_words[i + 2..], true)?;
@@ -66,14 +252,14 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
             "{" => {
                 let block = read_block(&str_words[i..], true)?;
                 i += block.2;
-                words.push(Word::Const(Value::Func(AFunc::new(Func {
-                    ret_count: block.0.ok_or(LexerError::FunctionBlockExpected)?,
-                    to_call: FuncImpl::SPL(block.1),
-                    origin: Arc::new(Frame::dummy()),
-                    fname: None,
-                    name: "dyn".to_owned(),
-                    run_as_base: false,
-                }))))
+                words.push(lambda(block.0, block.1)?);
+            }
+            // gen { | ... } is the same as { | ... } gen, and likewise for async
+            x @ ("gen" | "async") if str_words.get(i + 1).is_some_and(|x| x == "{") => {
+                let block = read_block(&str_words[i + 1..], true)?;
+                i += block.2 + 1;
+                words.push(lambda(block.0, block.1)?);
+                words.push(Word::Call(x.to_owned(), false, 0));
             }
             x if x.len() >= 2 && &x[0..2] == "!{" => {
                 words.push(Word::Const(Value::Str(x[2..].to_owned())));
@@ -107,32 +293,18 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
                 if str_words[i + 2] != "{" {
                     return Err(LexerError::InvalidConstructBlock);
                 }
-                let mut fields = Vec::new();
                 i += 3;
-                while str_words[i] != ";" && str_words[i] != "}" {
-                    fields.push(str_words[i].to_owned());
-                    i += 1;
-                }
-                let mut methods = Vec::new();
-                let mut has_construct = false;
-                if str_words[i] == ";" {
-                    i += 1;
-                    while str_words[i] != "}" {
-                        let name = str_words[i].to_owned();
-                        if name == "construct" {
-                            has_construct = true;
-                        }
-                        let block = read_block(&str_words[i + 1..], true)?;
-                        i += 1 + block.2;
-                        methods.push((
-                            name,
-                            (block.0.ok_or(LexerError::FunctionBlockExpected)?, block.1),
-                        ));
-                        i += 1;
-                    }
+                let fields = read_fields(str_words, &mut i)?;
+                if str_words[i] == ")" {
+                    return Err(LexerError::InvalidConstructBlock);
                 }
+                let mut methods = read_methods(str_words, &mut i)?;
+                let has_construct = methods.iter().any(|x| x.0 == "construct");
                 if !has_construct && !is_namespace {
-                    methods.push(("construct".to_string(), (1, Words { words: vec![] })));
+                    methods.push((
+                        "construct".to_string(),
+                        (Signature::any(1), Words { words: vec![] }),
+                    ));
                 }
                 words.push(Word::Key(Keyword::Construct(
                     name,
@@ -141,6 +313,55 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
                     is_namespace,
                 )));
             }
+            "interface" => {
+                let name = str_words[i + 1].to_owned();
+                if str_words[i + 2] != "{" {
+                    return Err(LexerError::InvalidInterfaceBlock);
+                }
+                let mut requires = Vec::new();
+                i += 3;
+                while str_words[i] != ";" && str_words[i] != "}" {
+                    let method = str_words[i].to_owned();
+                    let mut signature = None;
+                    if str_words[i + 1] == "{" {
+                        let block = read_block(&str_words[i + 1..], true)?;
+                        if !block.1.words.is_empty() {
+                            return Err(LexerError::InvalidInterfaceBlock);
+                        }
+                        signature = block.0;
+                        i += 1 + block.2;
+                    }
+                    requires.push((method, signature));
+                    i += 1;
+                }
+                let methods = read_methods(str_words, &mut i)?;
+                words.push(Word::Key(Keyword::Interface(name, requires, methods)));
+            }
+            "enum" => {
+                let name = str_words[i + 1].to_owned();
+                if str_words[i + 2] != "{" {
+                    return Err(LexerError::InvalidEnumBlock);
+                }
+                let mut variants = Vec::new();
+                i += 3;
+                while str_words[i] != ";" && str_words[i] != "}" {
+                    let variant = str_words[i].to_owned();
+                    let mut fields = Vec::new();
+                    if str_words[i + 1] == "(" {
+                        i += 2;
+                        fields = read_fields(str_words, &mut i)?;
+                        if str_words[i] != ")" {
+                            return Err(LexerError::InvalidEnumBlock);
+                        }
+                    } else if str_words[i + 1] == ")" {
+                        return Err(LexerError::InvalidEnumBlock);
+                    }
+                    variants.push((variant, fields));
+                    i += 1;
+                }
+                let methods = read_methods(str_words, &mut i)?;
+                words.push(Word::Key(Keyword::Enum(name, variants, methods)));
+            }
             "include" => {
                 if let Some(x) = readf(
                     "include\0{}\0in\0{}",
@@ -165,12 +386,85 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
                 i += 2 + cond.2;
                 let blk = read_block(&str_words[i + 2..], false)?;
                 i += 2 + blk.2;
+                if let Some(name) = find_const(&cond.1).or_else(|| find_const(&blk.1)) {
+                    return Err(LexerError::ConstInLoop(name));
+                }
                 words.push(Word::Key(Keyword::While(cond.1, blk.1)));
             }
+            "for" => {
+                if str_words[i + 2] != "in" {
+                    return Err(LexerError::InvalidForLoop);
+                }
+                let var = str_words[i + 1].clone();
+                i += 3;
+                let start = i;
+                while str_words[i] != "{" {
+                    i += 1;
+                }
+                let mut iterable = str_words[start..i].to_vec();
+                iterable.push("}".to_owned());
+                let blk = read_block(&str_words[i + 1..], false)?;
+                i += 1 + blk.2;
+                if let Some(name) = find_const(&blk.1) {
+                    return Err(LexerError::ConstInLoop(name));
+                }
+                words.push(Word::Key(Keyword::For(
+                    var,
+                    read_block(&iterable, false)?.1,
+                    blk.1,
+                )));
+            }
+            "break" => words.push(Word::Key(Keyword::Break)),
+            "continue" => words.push(Word::Key(Keyword::Continue)),
+            "return" => words.push(Word::Key(Keyword::Return)),
             "if" => {
                 let blk = read_block(&str_words[i + 2..], false)?;
                 i += 2 + blk.2;
-                words.push(Word::Key(Keyword::If(blk.1)));
+                words.push(Word::Key(match read_else(str_words, &mut i)? {
+                    Some(els) => Keyword::IfElse(blk.1, els),
+                    None => Keyword::If(blk.1),
+                }));
+            }
+            // only a keyword in front of a block, so it can still be used as a name
+            "match" if str_words[i + 1] == "{" => {
+                i += 2;
+                let mut arms = Vec::new();
+                let mut default = None;
+                while str_words[i] != "}" {
+                    let start = i;
+                    while str_words[i] != "{" {
+                        i += 1;
+                    }
+                    let blk = read_block(&str_words[i + 1..], false)?;
+                    if str_words[start..i] == ["_"] {
+                        default = Some(blk.1);
+                    } else {
+                        let mut pattern = str_words[start..i].to_vec();
+                        // <words>(<name> <...>) destructures instead of comparing.
+                        let names = match pattern.iter().position(|x| x == "(") {
+                            Some(open) => {
+                                if pattern.last().map(String::as_str) != Some(")") {
+                                    return Err(LexerError::InvalidMatchBlock);
+                                }
+                                let names = pattern[open + 1..pattern.len() - 1].to_vec();
+                                pattern.truncate(open);
+                                Some(names)
+                            }
+                            None => None,
+                        };
+                        pattern.push("}".to_owned());
+                        let words = read_block(&pattern, false)?.1;
+                        arms.push((
+                            match names {
+                                Some(names) => Pattern::Destructure(words, names),
+                                None => Pattern::Value(words),
+                            },
+                            blk.1,
+                        ));
+                    }
+                    i += 2 + blk.2;
+                }
+                words.push(Word::Key(Keyword::Match(arms, default)));
             }
             "catch" => {
                 let mut types = Vec::new();
@@ -181,9 +475,21 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
                 }
                 let blk = read_block(&str_words[i + 1..], false)?;
                 i += 1 + blk.2;
+                // `catch { ... } with { with err ; ... }`: the handler is the block after `with`.
+                if str_words.get(i + 1).map(String::as_str) == Some("with")
+                    && str_words.get(i + 2).map(String::as_str) == Some("{")
+                {
+                    i += 2;
+                }
                 let ctch = read_block(&str_words[i + 1..], false)?;
                 i += 1 + ctch.2;
-                words.push(Word::Key(Keyword::Catch(types, blk.1, ctch.1)))
+                let mut finally = None;
+                if str_words.get(i + 1).map(String::as_str) == Some("finally") {
+                    let blk = read_block(&str_words[i + 3..], false)?;
+                    i += 3 + blk.2;
+                    finally = Some(blk.1);
+                }
+                words.push(Word::Key(Keyword::Catch(types, blk.1, ctch.1, finally)))
             }
             "with" => {
                 let mut vars = Vec::new();
@@ -192,37 +498,33 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
                     vars.push(str_words[i].to_owned());
                     i += 1;
                 }
-                words.push(Word::Key(Keyword::With(vars)));
+                words.push(Word::Key(Keyword::With(parse_bindings(&vars)?)));
             }
             "}" => {
                 break;
             }
+            // only meaningful in enum declarations and match patterns
+            "(" | ")" => (),
             x if x.starts_with('\"') => {
                 words.push(Word::Const(Value::Str(x[1..].to_owned())));
             }
-            x if x.chars().all(|c| c.is_numeric() || c == '_' || c == '-')
-                && !x.starts_with('_')
-                && x.contains(char::is_numeric) =>
-            {
-                words.push(Word::Const(Value::Mega(
-                    x.parse()
-                        .map_err(|_| LexerError::InvalidNumber(x.to_owned()))?,
-                )));
-            }
+            "true" => words.push(Word::Const(Value::Bool(true))),
+            "false" => words.push(Word::Const(Value::Bool(false))),
             x if x
-                .chars()
-                .all(|c| c.is_numeric() || c == '.' || c == '_' || c == '-')
-                && !x.starts_with('_')
-                && x.contains(char::is_numeric) =>
+                .strip_prefix('-')
+                .unwrap_or(x)
+                .starts_with(|c: char| c.is_ascii_digit())
+                && !x.contains(':') =>
             {
-                words.push(Word::Const(Value::Double(
-                    x.parse()
-                        .map_err(|_| LexerError::InvalidNumber(x.to_owned()))?,
-                )));
+                words.push(Word::Const(
+                    parse_number(x).ok_or_else(|| LexerError::InvalidNumber(x.to_owned()))?,
+                ));
             }
             x => {
-                let mut word = x.split(':').next().unwrap(); // SAFETY: One item always exists after a split.
-                if !word.is_empty() {
+                let mut parts = x.split(':').peekable();
+                let mut word = parts.next().unwrap(); // SAFETY: One item always exists after a split.
+                let mut is_super = word == "super" && parts.peek().is_some();
+                if !word.is_empty() && !is_super {
                     let mut ra = 0;
                     while word.starts_with('&') {
                         ra += 1;
@@ -234,7 +536,20 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
                         words.push(Word::Call(word.to_owned(), false, ra));
                     }
                 }
-                for mut word in x.split(':').skip(1) {
+                while let Some(mut word) = parts.next() {
+                    // super:<method> calls a method of the object on the stack, like :<method>.
+                    if is_super {
+                        is_super = false;
+                        words.push(Word::Key(match word.strip_suffix(';') {
+                            Some(word) => Keyword::Super(word.to_owned(), true),
+                            None => Keyword::Super(word.to_owned(), false),
+                        }));
+                        continue;
+                    }
+                    if word == "super" && parts.peek().is_some() {
+                        is_super = true;
+                        continue;
+                    }
                     let mut ra = 0;
                     while word.starts_with('&') {
                         ra += 1;
@@ -253,32 +568,132 @@ fn read_block(str_words: &[String], isfn: bool) -> Result<(Option<u32>, Words, u
     Ok((rem, Words { words }, i))
 }
 
+/// Parses a number literal: decimal, or hexadecimal, octal or binary with a `0x`, `0o` or `0b`
+/// prefix. Digits can be separated by `_`. Without a suffix, numbers are `mega`, or `double` if
+/// they contain a point; the suffixes `i`, `l`, `n`, `f` and `m` make them `int`, `long`,
+/// `bigint`, `float` and `decimal`.
+fn parse_number(x: &str) -> Option<Value> {
+    let (negative, x) = match x.strip_prefix('-') {
+        Some(x) => (true, x),
+        None => (false, x),
+    };
+    let x = x.replace('_', "");
+    let radix = match x.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+    let digits = if radix == 10 { &x[..] } else { &x[2..] };
+    // f is a hexadecimal digit, so hexadecimal numbers can't be floats.
+    let (digits, suffix) = match digits.char_indices().last()? {
+        (i, c @ ('i' | 'l' | 'n')) => (&digits[..i], Some(c)),
+        (i, c @ ('f' | 'm')) if radix == 10 => (&digits[..i], Some(c)),
+        _ => (digits, None),
+    };
+    if digits.is_empty()
+        || !digits
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.')
+    {
+        return None;
+    }
+    if suffix == Some('m') {
+        let x = Decimal::parse(digits)?;
+        return Some(Value::Decimal(if negative { -x } else { x }));
+    }
+    if suffix == Some('f') || digits.contains('.') {
+        if radix != 10 || suffix.is_some_and(|x| x != 'f') {
+            return None;
+        }
+        let x: f64 = digits.parse().ok()?;
+        let x = if negative { -x } else { x };
+        return Some(match suffix {
+            Some(_) => Value::Float(x as f32),
+            None => Value::Double(x),
+        });
+    }
+    if suffix == Some('n') {
+        let x = BigInt::from_str_radix(digits, radix)?;
+        return Some(Value::BigInt(if negative { -x } else { x }));
+    }
+    let x = i128::from_str_radix(digits, radix).ok()?;
+    let x = if negative { -x } else { x };
+    Some(match suffix {
+        Some('i') => Value::Int(x.try_into().ok()?),
+        Some('l') => Value::Long(x.try_into().ok()?),
+        _ => Value::Mega(x),
+    })
+}
+
 fn parse(input: String) -> Vec<String> {
     let mut words = Vec::new();
     let mut s = String::new();
 
     let mut exclam = false;
     let mut raw = 0;
+    let mut fstring = false;
+    // Where the words of the last f-string start, to drop them again if it turns out to be a
+    // comment.
+    let mut fstring_start = 0;
+    let mut in_comment = false;
+    // Whether each open paren is attached to a word, like in `Variant(field)`. Attached parens are
+    // kept as their own words, others are dropped.
+    let mut parens = Vec::new();
 
     for line in input.split('\n') {
         let mut in_string = false;
         let mut escaping = false;
         let mut was_in_string = false;
-        for c in line.chars() {
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            if in_comment {
+                if c == '*' && chars.as_str().starts_with('/') {
+                    chars.next();
+                    in_comment = false;
+                }
+                continue;
+            }
             if in_string {
                 if escaping {
                     if raw == 0 {
-                        if c == '\\' {
-                            s += "\\";
-                        }
-                        if c == 'n' {
-                            s += "\n";
-                        }
-                        if c == 'r' {
-                            s += "\r";
-                        }
-                        if c == '"' {
-                            s += "\"";
+                        match c {
+                            '\\' => s.push('\\'),
+                            'n' => s.push('\n'),
+                            'r' => s.push('\r'),
+                            't' => s.push('\t'),
+                            '0' => s.push('\0'),
+                            '"' => s.push('"'),
+                            'x' | 'u' => {
+                                // \x41 or \u{1F600}, kept as written if invalid.
+                                let rest = chars.as_str();
+                                let code = if c == 'x' {
+                                    rest.get(..2)
+                                } else {
+                                    rest.strip_prefix('{')
+                                        .and_then(|x| x.split_once('}'))
+                                        .map(|x| x.0)
+                                };
+                                match code
+                                    .filter(|x| x.chars().all(|c| c.is_ascii_hexdigit()))
+                                    .and_then(|x| u32::from_str_radix(x, 16).ok())
+                                    .and_then(char::from_u32)
+                                {
+                                    Some(x) => {
+                                        s.push(x);
+                                        let len = code.unwrap().len();
+                                        chars.nth(if c == 'x' { len - 1 } else { len + 1 });
+                                    }
+                                    None => {
+                                        s.push('\\');
+                                        s.push(c);
+                                    }
+                                }
+                            }
+                            c => {
+                                s.push('\\');
+                                s.push(c);
+                            }
                         }
                         escaping = false;
                         continue;
@@ -289,6 +704,10 @@ fn parse(input: String) -> Vec<String> {
                     in_string = false;
                     escaping = false;
                     was_in_string = true;
+                    if fstring {
+                        fstring_start = words.len();
+                        words.extend(lower_fstring(&mem::take(&mut s)[1..]));
+                    }
                     if raw == 0 {
                         continue;
                     }
@@ -300,17 +719,48 @@ fn parse(input: String) -> Vec<String> {
                     }
                 }
             } else {
+                if raw == 0 && s.is_empty() {
+                    if c == '#' || (c == '/' && chars.as_str().starts_with('/')) {
+                        break;
+                    }
+                    if c == '/' && chars.as_str().starts_with('*') {
+                        chars.next();
+                        in_comment = true;
+                        continue;
+                    }
+                }
                 if c == '"' {
+                    fstring = s == "f" && raw == 0;
+                    if fstring {
+                        s = String::new();
+                    }
                     s += "\"";
                     in_string = true;
                     continue;
                 }
                 if raw == 0 {
                     if c == ';' && was_in_string {
+                        if fstring {
+                            words.truncate(fstring_start);
+                        }
                         s = String::new();
                         continue;
                     }
-                    if c == '(' || c == ')' {
+                    if c == '(' {
+                        parens.push(!s.is_empty());
+                        if !s.is_empty() {
+                            words.push(mem::take(&mut s));
+                            words.push("(".to_owned());
+                        }
+                        continue;
+                    }
+                    if c == ')' {
+                        if parens.pop() == Some(true) {
+                            if !s.is_empty() {
+                                words.push(mem::take(&mut s));
+                            }
+                            words.push(")".to_owned());
+                        }
                         continue;
                     }
                     if c == ' ' || c == '\t' {
@@ -354,3 +804,218 @@ fn parse(input: String) -> Vec<String> {
     }
     words
 }
+
+/// Turns the contents of an f-string into the words evaluating each embedded expression, followed
+/// by a format string with placeholders in their place and `format`. Like in format strings, `{{`
+/// and `}}` are literal braces. An expression can be followed by `::<spec>`, with two colons
+/// because a single one calls a method.
+fn lower_fstring(content: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut fmt = "\"".to_owned();
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                fmt += "{{";
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                fmt += "}}";
+            }
+            '{' => {
+                let mut expr = String::new();
+                let mut depth = 1;
+                for c in chars.by_ref() {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    expr.push(c);
+                }
+                let (expr, spec) = match expr.split_once("::") {
+                    Some((expr, spec)) if FormatSpec::parse(spec).is_some() => (expr, spec),
+                    _ => (expr.as_str(), ""),
+                };
+                words.extend(parse(expr.to_owned()));
+                fmt += "{";
+                if !spec.is_empty() {
+                    fmt += ":";
+                    fmt += spec;
+                }
+                fmt += "}";
+            }
+            '}' => fmt += "}}",
+            c => fmt.push(c),
+        }
+    }
+    words.push(fmt);
+    words.push("format".to_owned());
+    words
+}
+
+/// The kind of a [Token].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Any word, including keywords, numbers and parentheses glued to it.
+    Word,
+    /// A string literal.
+    Str,
+    /// A comment, which is dropped by the lexer: `# ...` or `// ...` until the end of the line,
+    /// `/* ... */`, or a string literal directly followed by `;`, as in `"this is a comment";`.
+    Comment,
+    /// A raw block, as in `!{ ... }`.
+    Raw,
+}
+
+/// A token of SPL source exactly as written.
+///
+/// Unlike [lex], [tokenize] doesn't throw anything away, so tooling that has to reproduce the
+/// source (like the formatter) can work on these.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// The source text, including quotes, escapes, and the `;` of comments.
+    pub text: String,
+    /// The 0-based line the token starts on.
+    pub line: usize,
+    /// The 0-based column (in chars) the token starts at.
+    pub column: usize,
+}
+
+impl Token {
+    /// The line the token ends on. Only differs from `line` for multi-line raw blocks.
+    pub fn end_line(&self) -> usize {
+        self.line + self.text.matches('\n').count()
+    }
+}
+
+/// Splits SPL source into [Token]s the same way the lexer does, but keeps comments and the exact
+/// text of every token.
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    let (mut line, mut column) = (0, 0);
+
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            chars.next();
+            line += 1;
+            column = 0;
+            continue;
+        }
+        if c.is_whitespace() {
+            chars.next();
+            column += 1;
+            continue;
+        }
+
+        let mut token = Token {
+            kind: if c == '"' {
+                TokenKind::Str
+            } else {
+                TokenKind::Word
+            },
+            text: String::new(),
+            line,
+            column,
+        };
+        let mut ahead = chars.clone();
+        ahead.next();
+        let next = ahead.peek().copied();
+        if c == '#' || (c == '/' && next == Some('/')) {
+            token.kind = TokenKind::Comment;
+            while let Some(c) = chars.next_if(|x| *x != '\n') {
+                column += 1;
+                token.text.push(c);
+            }
+            tokens.push(token);
+            continue;
+        }
+        if c == '/' && next == Some('*') {
+            token.kind = TokenKind::Comment;
+            for c in chars.by_ref() {
+                token.text.push(c);
+                if c == '\n' {
+                    line += 1;
+                    column = 0;
+                } else {
+                    column += 1;
+                }
+                if token.text.len() > 3 && token.text.ends_with("*/") {
+                    break;
+                }
+            }
+            tokens.push(token);
+            continue;
+        }
+        let mut exclam = false;
+        while let Some(&c) = chars.peek() {
+            if c == '\n' || c == '\r' || c == ' ' || c == '\t' {
+                break;
+            }
+            chars.next();
+            column += 1;
+            token.text.push(c);
+            if c == '"' {
+                if token.text == "f\"" {
+                    token.kind = TokenKind::Str;
+                }
+                // strings end at their closing quote or the end of the line.
+                let mut escaping = false;
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                    column += 1;
+                    token.text.push(c);
+                    if escaping {
+                        escaping = false;
+                    } else if c == '\\' {
+                        escaping = true;
+                    } else if c == '"' {
+                        break;
+                    }
+                }
+                if chars.peek() == Some(&';') {
+                    chars.next();
+                    column += 1;
+                    token.text.push(';');
+                    token.kind = TokenKind::Comment;
+                    break;
+                }
+            } else if c == '{' && exclam {
+                // raw blocks end at the matching brace, even across lines.
+                token.kind = TokenKind::Raw;
+                let mut raw = 1;
+                for c in chars.by_ref() {
+                    token.text.push(c);
+                    if c == '\n' {
+                        line += 1;
+                        column = 0;
+                    } else {
+                        column += 1;
+                    }
+                    if c == '{' {
+                        raw += 1;
+                    }
+                    if c == '}' {
+                        raw -= 1;
+                        if raw == 0 {
+                            break;
+                        }
+                    }
+                }
+                break;
+            }
+            exclam = c == '!';
+        }
+        tokens.push(token);
+    }
+    tokens
+}
@@ -25,9 +25,16 @@
 
 #![allow(clippy::type_complexity)]
 #![allow(clippy::len_without_is_empty)]
+#![allow(clippy::arc_with_non_send_sync)]
 
+pub mod bignum;
+pub mod checker;
+pub mod docgen;
 pub mod dyn_fns;
+pub mod formatter;
+pub mod generator;
 pub mod lexer;
+pub mod linter;
 pub mod mutex;
 pub mod oxidizer;
 pub mod runtime;
@@ -113,6 +120,7 @@ nofmt! {
     #[macro_export]
     macro_rules! require_mut_array {
         ($name:tt, $array:expr, $stack:expr, $fn:literal) => {
+            $array.lock_ro().check_mutable($stack)?;
             let Value::Array(ref mut $name) = $array.lock().native else {
                 return $stack.err(ErrorKind::InvalidCall($fn.to_owned()))
             };
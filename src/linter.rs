@@ -0,0 +1,561 @@
+//! The SPL linter, used by `spl lint`.
+//!
+//! The linter looks for common mistakes in [Words]. Every [Rule] can be enabled and disabled on
+//! its own. Variables are resolved the way the interpreter does it: a function or lambda can see
+//! the variables of the code it was defined in, and everything defined at the top level of a file
+//! or in the standard library.
+
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    checker::{Checker, WarningKind},
+    lexer::lex,
+    stdlib, Binding, FuncImpl, Keyword, Pattern, Stack, Value, Word, Words,
+};
+
+/// A check done by the [Linter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// `def x` without any `=x`. Variables defined at the top level are ignored, because other
+    /// files can assign them.
+    DefWithoutAssign,
+    /// `=x` without `x` being defined anywhere it could be seen from.
+    AssignUndeclared,
+    /// `with x ;` where `x` is already a variable. Names starting with `_` are ignored, and so are
+    /// functions, which are often deliberately taken as arguments under their own name.
+    ShadowedWith,
+    /// A variable in a function that is never read. Variables starting with `_` and `this`, which
+    /// methods have to take either way, are ignored.
+    UnusedVariable,
+    /// Calls to the deprecated `_` function.
+    DeprecatedUnderscore,
+    /// A function leaving more values than it declares, usually because of a missing `;`.
+    UnusedResult,
+    /// An int used as a condition, like `1 if { ... }`, or a variable that is only ever assigned
+    /// int literals used as one. Ints still work, but `true` and `false` say what is meant.
+    IntCondition,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::DefWithoutAssign,
+        Rule::AssignUndeclared,
+        Rule::ShadowedWith,
+        Rule::UnusedVariable,
+        Rule::DeprecatedUnderscore,
+        Rule::UnusedResult,
+        Rule::IntCondition,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::DefWithoutAssign => "def-without-assign",
+            Rule::AssignUndeclared => "assign-undeclared",
+            Rule::ShadowedWith => "shadowed-with",
+            Rule::UnusedVariable => "unused-variable",
+            Rule::DeprecatedUnderscore => "deprecated-underscore",
+            Rule::UnusedResult => "unused-result",
+            Rule::IntCondition => "int-condition",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|x| x.name() == name)
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A problem found by the [Linter].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub rule: Rule,
+    /// Where the problem is, for example `func main` or `method List:push`.
+    pub location: String,
+    pub message: String,
+}
+
+impl Lint {
+    /// Writes the lint as a JSON object.
+    pub fn to_json(&self, file: &str) -> String {
+        format!(
+            "{{\"file\":{},\"rule\":{},\"location\":{},\"message\":{}}}",
+            json_str(file),
+            json_str(self.rule.name()),
+            json_str(&self.location),
+            json_str(&self.message)
+        )
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.rule, self.location, self.message)
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut output = String::with_capacity(s.len() + 2);
+    output += "\"";
+    for c in s.chars() {
+        match c {
+            '"' => output += "\\\"",
+            '\\' => output += "\\\\",
+            '\n' => output += "\\n",
+            '\r' => output += "\\r",
+            '\t' => output += "\\t",
+            c if (c as u32) < 0x20 => output += &format!("\\u{:04x}", c as u32),
+            c => output.push(c),
+        }
+    }
+    output += "\"";
+    output
+}
+
+/// The variables a scope declares, and the names it (or anything nested in it) reads and assigns.
+#[derive(Default)]
+struct Scope {
+    defs: Vec<String>,
+    withs: Vec<String>,
+    reads: HashSet<String>,
+    assigns: HashSet<String>,
+}
+
+impl Scope {
+    fn of(body: &Words) -> Scope {
+        let mut scope = Scope::default();
+        scope.declarations(body);
+        scope.references(body);
+        scope
+    }
+
+    fn declares(&self, name: &str) -> bool {
+        self.defs.iter().chain(&self.withs).any(|x| x == name)
+    }
+
+    fn declarations(&mut self, words: &Words) {
+        for word in &words.words {
+            match word {
+                Word::Key(Keyword::Def(name)) => self.defs.push(name.clone()),
+                Word::Key(Keyword::Const(name, value)) => {
+                    self.defs.push(name.clone());
+                    self.assigns.insert(name.clone());
+                    self.declarations(value);
+                }
+                Word::Key(Keyword::Use(item)) => {
+                    self.defs
+                        .push(item.rsplit(':').next().unwrap_or(item).to_owned());
+                    self.assigns
+                        .insert(item.rsplit(':').next().unwrap_or(item).to_owned());
+                }
+                Word::Key(Keyword::With(bindings)) => {
+                    self.withs.extend(bindings.iter().flat_map(Binding::names))
+                }
+                Word::Key(Keyword::For(var, iterable, blk)) => {
+                    self.defs.push(var.clone());
+                    self.assigns.insert(var.clone());
+                    self.declarations(iterable);
+                    self.declarations(blk);
+                }
+                Word::Key(
+                    Keyword::Construct(name, ..)
+                    | Keyword::Interface(name, ..)
+                    | Keyword::Enum(name, ..),
+                ) if !name.contains(':') => {
+                    self.defs.push(name.clone());
+                    self.assigns.insert(name.clone());
+                }
+                Word::Key(key) => {
+                    if let Keyword::Match(arms, _) = key {
+                        for (pattern, _) in arms {
+                            if let Pattern::Destructure(_, names) = pattern {
+                                for name in names.iter().filter(|x| *x != "_") {
+                                    self.defs.push(name.clone());
+                                    self.assigns.insert(name.clone());
+                                }
+                            }
+                        }
+                    }
+                    key.blocks()
+                        .into_iter()
+                        .for_each(|blk| self.declarations(blk))
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn references(&mut self, words: &Words) {
+        for_each_word(words, &mut |word| {
+            if let Word::Call(name, ..) = word {
+                match name.strip_prefix('=') {
+                    Some(var) => self.assigns.insert(var.to_owned()),
+                    None => self.reads.insert(name.to_owned()),
+                };
+            }
+        });
+    }
+}
+
+/// Calls `f` on every word, including those in nested blocks, functions and lambdas.
+fn for_each_word(words: &Words, f: &mut impl FnMut(&Word)) {
+    for word in &words.words {
+        f(word);
+        for body in nested(word) {
+            for_each_word(body, f);
+        }
+    }
+}
+
+/// The blocks directly contained in a word.
+fn nested(word: &Word) -> Vec<&Words> {
+    match word {
+        Word::Key(Keyword::Func(_, _, body)) => vec![body],
+        Word::Key(Keyword::Construct(_, fields, methods, _)) => fields
+            .iter()
+            .filter_map(|x| x.default.as_ref())
+            .chain(methods.iter().map(|(_, (_, body))| body))
+            .collect(),
+        Word::Key(Keyword::Interface(_, _, methods)) => {
+            methods.iter().map(|(_, (_, body))| body).collect()
+        }
+        Word::Key(Keyword::Enum(_, variants, methods)) => variants
+            .iter()
+            .flat_map(|x| &x.1)
+            .filter_map(|x| x.default.as_ref())
+            .chain(methods.iter().map(|(_, (_, body))| body))
+            .collect(),
+        Word::Key(key) => key.blocks(),
+        Word::Const(Value::Func(f)) => match f.to_call {
+            FuncImpl::SPL(ref body) => vec![body],
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// The functions and methods directly contained in a block (not in nested functions), with their
+/// locations.
+fn scopes<'a>(words: &'a Words, location: &str, output: &mut Vec<(String, &'a Words)>) {
+    for word in &words.words {
+        match word {
+            Word::Key(Keyword::Func(name, _, body)) => output.push((format!("func {name}"), body)),
+            Word::Key(
+                Keyword::Construct(name, _, methods, _)
+                | Keyword::Interface(name, _, methods)
+                | Keyword::Enum(name, _, methods),
+            ) => {
+                for (method, (_, body)) in methods {
+                    output.push((format!("method {name}:{method}"), body));
+                }
+            }
+            Word::Const(Value::Func(f)) => {
+                if let FuncImpl::SPL(ref body) = f.to_call {
+                    output.push((format!("{location} > lambda"), body));
+                }
+            }
+            word => {
+                for body in nested(word) {
+                    scopes(body, location, output);
+                }
+            }
+        }
+    }
+}
+
+/// Looks for common mistakes in SPL code.
+///
+/// ```
+/// use spl::linter::{Linter, Rule};
+/// let words = spl::lex("func main { mega | with args ; def x 0 }".to_owned()).unwrap();
+/// let lints = Linter::new().lint(&words);
+/// assert_eq!(lints.len(), 3);
+/// assert_eq!(lints[0].rule, Rule::DefWithoutAssign);
+/// ```
+pub struct Linter {
+    rules: HashSet<Rule>,
+    /// Names of all functions and variables defined by the natives and the standard library.
+    globals: HashSet<String>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    /// Creates a linter with all rules enabled.
+    pub fn new() -> Self {
+        let mut globals: HashSet<String> = Stack::new()
+            .get_frame()
+            .functions
+            .lock_ro()
+            .keys()
+            .cloned()
+            .collect();
+        for (_, source) in stdlib::FILES {
+            if let Ok(words) = lex(source.to_string()) {
+                Self::collect_globals(&words, &mut globals);
+            }
+        }
+        Linter {
+            rules: Rule::ALL.into_iter().collect(),
+            globals,
+        }
+    }
+
+    pub fn enable(&mut self, rule: Rule) {
+        self.rules.insert(rule);
+    }
+
+    pub fn disable(&mut self, rule: Rule) {
+        self.rules.remove(&rule);
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        self.rules.contains(&rule)
+    }
+
+    /// Adds the names of the functions and variables defined at the top level.
+    fn collect_globals(words: &Words, globals: &mut HashSet<String>) {
+        let scope = Scope::of(words);
+        for name in scope.defs {
+            globals.insert("=".to_owned() + &name);
+            globals.insert(name);
+        }
+        for word in &words.words {
+            if let Word::Key(Keyword::Func(name, ..)) = word {
+                globals.insert(name.clone());
+            }
+        }
+    }
+
+    /// Lints some code, returning all problems found.
+    pub fn lint(&self, words: &Words) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        let mut globals = self.globals.clone();
+        Self::collect_globals(words, &mut globals);
+        self.scope(
+            words,
+            "top level".to_owned(),
+            &mut Vec::new(),
+            &globals,
+            &mut lints,
+        );
+        if self.is_enabled(Rule::UnusedResult) {
+            for warning in Checker::with_std().check(words) {
+                if let WarningKind::ReturnCount { declared, leaves } = warning.kind {
+                    if leaves > declared as i64 {
+                        lints.push(Lint {
+                            rule: Rule::UnusedResult,
+                            location: warning.location,
+                            message: format!(
+                                "leaves {} more values than it declares, is a `;` missing?",
+                                leaves - declared as i64
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        lints
+    }
+
+    fn scope(
+        &self,
+        body: &Words,
+        location: String,
+        outer: &mut Vec<Scope>,
+        globals: &HashSet<String>,
+        lints: &mut Vec<Lint>,
+    ) {
+        let scope = Scope::of(body);
+        let mut lint = |rule, message| {
+            if self.is_enabled(rule) {
+                lints.push(Lint {
+                    rule,
+                    location: location.clone(),
+                    message,
+                });
+            }
+        };
+        let visible = |name: &str| {
+            scope.declares(name) || outer.iter().any(|x| x.declares(name)) || globals.contains(name)
+        };
+
+        // variables defined at the top level can be used by other files.
+        let top_level = outer.is_empty();
+        for name in &scope.defs {
+            if !top_level && !scope.assigns.contains(name) {
+                lint(
+                    Rule::DefWithoutAssign,
+                    format!("`{name}` is defined, but never assigned"),
+                );
+            }
+        }
+        for name in &scope.withs {
+            if !name.starts_with('_')
+                && (outer.iter().any(|x| x.declares(name)) || globals.contains(&format!("={name}")))
+            {
+                lint(
+                    Rule::ShadowedWith,
+                    format!("`with {name} ;` shadows an existing variable"),
+                );
+            }
+        }
+        for name in scope.defs.iter().chain(&scope.withs) {
+            if !top_level && !name.starts_with('_') && name != "this" && !scope.reads.contains(name)
+            {
+                lint(Rule::UnusedVariable, format!("`{name}` is never used"));
+            }
+        }
+        let mut nested = Vec::new();
+        let mut direct = Vec::new();
+        scopes(body, &location, &mut nested);
+        collect_direct_calls(body, &mut direct);
+        for name in direct {
+            if let Some(var) = name.strip_prefix('=') {
+                if !visible(var) && !globals.contains(name) {
+                    lint(
+                        Rule::AssignUndeclared,
+                        format!("`{name}` assigns to `{var}`, which is never defined"),
+                    );
+                }
+            } else if name == "_" {
+                lint(Rule::DeprecatedUnderscore, "`_` is deprecated".to_owned());
+            }
+        }
+
+        let mut int_vars = HashSet::new();
+        let mut other_vars = HashSet::new();
+        collect_int_vars(body, &mut int_vars, &mut other_vars);
+        int_vars.retain(|x| scope.defs.contains(x) && !other_vars.contains(x));
+        for message in int_conditions(body, &int_vars) {
+            lint(Rule::IntCondition, message);
+        }
+
+        outer.push(scope);
+        for (location, body) in nested {
+            self.scope(body, location, outer, globals, lints);
+        }
+        outer.pop();
+    }
+}
+
+/// Sorts the variables assigned in a block (excluding nested functions) by whether they are only
+/// assigned int literals.
+fn collect_int_vars(words: &Words, ints: &mut HashSet<String>, others: &mut HashSet<String>) {
+    let mut previous: Option<&Word> = None;
+    for word in &words.words {
+        match word {
+            Word::Call(name, ..) => {
+                if let Some(var) = name.strip_prefix('=') {
+                    if matches!(previous, Some(Word::Const(x)) if is_int(x)) {
+                        ints.insert(var.to_owned());
+                    } else {
+                        others.insert(var.to_owned());
+                    }
+                }
+            }
+            Word::Key(
+                Keyword::Func(..)
+                | Keyword::Construct(..)
+                | Keyword::Interface(..)
+                | Keyword::Enum(..),
+            )
+            | Word::Const(_) => (),
+            word => {
+                for body in nested(word) {
+                    collect_int_vars(body, ints, others);
+                }
+            }
+        }
+        previous = Some(word);
+    }
+}
+
+fn is_int(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Int(_) | Value::Long(_) | Value::Mega(_) | Value::BigInt(_)
+    )
+}
+
+/// Finds the ints used as conditions of if, while, not, and and or in a block (excluding nested
+/// functions), given the variables only assigned ints.
+fn int_conditions(words: &Words, int_vars: &HashSet<String>) -> Vec<String> {
+    let describe = |word: Option<&Word>| match word {
+        Some(Word::Const(Value::Int(x))) => Some(format!("the int `{x}i`")),
+        Some(Word::Const(Value::Long(x))) => Some(format!("the int `{x}l`")),
+        Some(Word::Const(Value::Mega(x))) => Some(format!("the int `{x}`")),
+        Some(Word::Const(Value::BigInt(x))) => Some(format!("the int `{x}n`")),
+        Some(Word::Call(name, false, 0)) if int_vars.contains(name) => {
+            Some(format!("`{name}`, which is only assigned ints,"))
+        }
+        _ => None,
+    };
+    let mut output = Vec::new();
+    let mut previous = None;
+    for word in &words.words {
+        let used_by = match word {
+            Word::Key(Keyword::If(_) | Keyword::IfElse(..)) => {
+                describe(previous).map(|x| (x, "if"))
+            }
+            Word::Key(Keyword::While(cond, _)) => describe(cond.words.last()).map(|x| (x, "while")),
+            Word::Call(name, ..) if matches!(name.as_str(), "not" | "and" | "or") => {
+                describe(previous).map(|x| (x, name.as_str()))
+            }
+            _ => None,
+        };
+        if let Some((value, used_by)) = used_by {
+            output.push(format!(
+                "{value} is used as a condition of `{used_by}`, use `true` or `false` instead"
+            ));
+        }
+        match word {
+            Word::Key(
+                Keyword::Func(..)
+                | Keyword::Construct(..)
+                | Keyword::Interface(..)
+                | Keyword::Enum(..),
+            )
+            | Word::Const(Value::Func(_)) => (),
+            word => {
+                for body in nested(word) {
+                    output.append(&mut int_conditions(body, int_vars));
+                }
+            }
+        }
+        previous = Some(word);
+    }
+    output
+}
+
+/// The names of all functions called in a block, excluding nested functions.
+fn collect_direct_calls<'a>(words: &'a Words, output: &mut Vec<&'a str>) {
+    for word in &words.words {
+        match word {
+            Word::Call(name, ..) => output.push(name),
+            Word::Key(
+                Keyword::Func(..)
+                | Keyword::Construct(..)
+                | Keyword::Interface(..)
+                | Keyword::Enum(..),
+            )
+            | Word::Const(_) => (),
+            word => {
+                for body in nested(word) {
+                    collect_direct_calls(body, output);
+                }
+            }
+        }
+    }
+}
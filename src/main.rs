@@ -1,12 +1,223 @@
-use spl::{find_in_splpath, lex, oxidizer::RustAppBuilder, start_file};
+use spl::{
+    checker::Checker,
+    docgen, find_in_splpath, formatter, lex,
+    linter::{Linter, Rule},
+    oxidizer::RustAppBuilder,
+    start_file, stdlib,
+};
 
-use std::{env::args, fs};
+use std::{
+    env::args,
+    fs,
+    io::{stdin, Read},
+    process,
+};
+
+fn fmt(args: impl Iterator<Item = String>) {
+    let mut check = false;
+    let mut files = Vec::new();
+    for arg in args {
+        if arg == "--check" {
+            check = true;
+        } else {
+            files.push(arg);
+        }
+    }
+    if files.is_empty() {
+        let mut data = String::new();
+        stdin()
+            .read_to_string(&mut data)
+            .expect("unable to read stdin");
+        let formatted = formatter::format(&data);
+        if check {
+            process::exit(if formatted == data { 0 } else { 1 });
+        }
+        print!("{formatted}");
+        return;
+    }
+    let mut unformatted = false;
+    for file in files {
+        let data = fs::read_to_string(&file).expect("unable to read specified file");
+        let formatted = formatter::format(&data);
+        if formatted == data {
+            continue;
+        }
+        if check {
+            println!("{file} is not formatted.");
+            unformatted = true;
+        } else {
+            fs::write(&file, formatted).expect("unable to write specified file");
+        }
+    }
+    if unformatted {
+        process::exit(1);
+    }
+}
+
+fn check(args: impl Iterator<Item = String>) {
+    let mut found = false;
+    for file in args {
+        let data = fs::read_to_string(&file).expect("unable to read specified file");
+        let words = match lex(data) {
+            Ok(x) => x,
+            Err(x) => {
+                println!("{file}: {x:?}");
+                found = true;
+                continue;
+            }
+        };
+        for warning in Checker::with_std().check(&words) {
+            println!("{file}: {warning}");
+            found = true;
+        }
+    }
+    if found {
+        process::exit(1);
+    }
+}
+
+fn lint(mut args: impl Iterator<Item = String>) {
+    let mut linter = Linter::new();
+    let mut json = false;
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => {
+                println!("Usage: spl lint [--json] [--enable <rule>] [--disable <rule>] <files>");
+                let rules: Vec<_> = Rule::ALL.iter().map(Rule::name).collect();
+                println!("Rules: {}, or all", rules.join(", "));
+                return;
+            }
+            "--json" => json = true,
+            "--enable" | "--disable" => {
+                let name = args.next().expect("no rule given");
+                let rules = if name == "all" {
+                    Rule::ALL.to_vec()
+                } else {
+                    vec![Rule::from_name(&name).unwrap_or_else(|| panic!("unknown rule {name}"))]
+                };
+                for rule in rules {
+                    if arg == "--enable" {
+                        linter.enable(rule);
+                    } else {
+                        linter.disable(rule);
+                    }
+                }
+            }
+            _ => files.push(arg),
+        }
+    }
+    let mut found = Vec::new();
+    for file in files {
+        let data = fs::read_to_string(&file).expect("unable to read specified file");
+        match lex(data) {
+            Ok(words) => found.extend(linter.lint(&words).into_iter().map(|x| (file.clone(), x))),
+            Err(x) => {
+                println!("{file}: {x:?}");
+                process::exit(1);
+            }
+        }
+    }
+    if json {
+        let lints: Vec<_> = found.iter().map(|(file, x)| x.to_json(file)).collect();
+        println!("[{}]", lints.join(","));
+    } else {
+        for (file, lint) in &found {
+            println!("{file}: {lint}");
+        }
+    }
+    if !found.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn doc(mut args: impl Iterator<Item = String>) {
+    let mut out = "doc".to_owned();
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--out" {
+            out = args.next().expect("no output directory given");
+        } else {
+            files.push(arg);
+        }
+    }
+    let sources: Vec<(String, String)> = if files.is_empty() {
+        [
+            "std.spl",
+            "iter.spl",
+            "stream.spl",
+            "http.spl",
+            "messaging.spl",
+            "async.spl",
+        ]
+        .into_iter()
+        .map(|name| {
+            let (_, source) = stdlib::FILES.iter().find(|x| x.0 == name).unwrap();
+            (name.to_owned(), source.to_string())
+        })
+        .collect()
+    } else {
+        files
+            .into_iter()
+            .map(|file| {
+                let data = fs::read_to_string(&file).expect("unable to read specified file");
+                (file, data)
+            })
+            .collect()
+    };
+    let modules: Vec<_> = sources
+        .iter()
+        .map(|(file, source)| {
+            let name = file.rsplit('/').next().unwrap_or(file);
+            let name = name.strip_suffix(".spl").unwrap_or(name);
+            docgen::document(name, source).unwrap_or_else(|x| panic!("{file}: {x:?}"))
+        })
+        .collect();
+    let index = docgen::Index::new(&modules);
+    fs::create_dir_all(&out).expect("unable to create output directory");
+    let mut md = "# Modules\n\n".to_owned();
+    let mut html = "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Modules</title>\n</head>\n<body>\n<h1>Modules</h1>\n<ul>\n".to_owned();
+    for module in &modules {
+        fs::write(
+            format!("{out}/{}.md", module.name),
+            module.to_markdown(&index),
+        )
+        .expect("unable to write documentation");
+        fs::write(
+            format!("{out}/{}.html", module.name),
+            module.to_html(&index),
+        )
+        .expect("unable to write documentation");
+        md += &format!("- [{0}]({0}.md)\n", module.name);
+        html += &format!("<li><a href=\"{0}.html\">{0}</a></li>\n", module.name);
+    }
+    html += "</ul>\n</body>\n</html>\n";
+    fs::write(format!("{out}/index.md"), md).expect("unable to write documentation");
+    fs::write(format!("{out}/index.html"), html).expect("unable to write documentation");
+    println!("Documentation written to {out}/");
+}
 
 fn main() {
     let mut args = args().skip(1);
     let arg = &args
         .next()
         .unwrap_or_else(|| find_in_splpath("repl.spl").expect("no file to be run"));
+    if arg == "fmt" {
+        fmt(args);
+        return;
+    }
+    if arg == "doc" {
+        doc(args);
+        return;
+    }
+    if arg == "lint" {
+        lint(args);
+        return;
+    }
+    if arg == "check" {
+        check(args);
+        return;
+    }
     if arg == "--build" || arg == "--run" {
         let file = args.next().unwrap();
         let data = fs::read_to_string(file.clone()).expect("unable to read specified file");
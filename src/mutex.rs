@@ -11,11 +11,11 @@ impl<T> Mut<T> {
         Mut(RwLock::new(obj))
     }
 
-    pub fn lock_ro(&self) -> RwLockReadGuard<T> {
+    pub fn lock_ro(&self) -> RwLockReadGuard<'_, T> {
         self.0.read().unwrap()
     }
 
-    pub fn lock(&self) -> RwLockWriteGuard<T> {
+    pub fn lock(&self) -> RwLockWriteGuard<'_, T> {
         self.0.write().unwrap()
     }
 }
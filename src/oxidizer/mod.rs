@@ -65,7 +65,7 @@ impl Hash for RustAppBuilder {
         for f in &self.rust_functions {
             f.fn_name.hash(state);
         }
-        for (k, _) in &self.to_embed {
+        for k in self.to_embed.keys() {
             k.hash(state);
         }
     }
@@ -95,12 +95,9 @@ impl RustAppBuilder {
     pub fn prepare(&mut self, spl: Words) -> bool {
         let mut needs_new = false;
         for word in spl.words {
-            match word {
-                Word::Key(Keyword::FuncOf(name, content, FuncImplType::Rust)) => {
-                    self.rust_functions.push(splrs::to_rust(name, content));
-                    needs_new = true;
-                }
-                _ => (),
+            if let Word::Key(Keyword::FuncOf(name, content, FuncImplType::Rust)) = word {
+                self.rust_functions.push(splrs::to_rust(name, content));
+                needs_new = true;
             }
         }
         needs_new
@@ -206,7 +203,7 @@ impl RustAppBuilder {
                 let dir = format!("{tmp}/spl-{name}/target/release/");
                 fs::read_dir(dir)
                     .expect("unable to build: dir was not created.")
-                    .filter(|x| {
+                    .find(|x| {
                         let x = x
                             .as_ref()
                             .expect("file system did something i cannot comprehend");
@@ -215,7 +212,6 @@ impl RustAppBuilder {
                             && !n.ends_with(".d")
                             && !n.starts_with(".")
                     })
-                    .next()
                     .expect("cargo was unable to build the binary")
                     .expect("file system did something i cannot comprehend")
                     .path()
@@ -1,5 +1,6 @@
 use crate::{
-    dyn_fns,
+    bignum::*,
+    dyn_fns, generator,
     mutex::*,
     std_fns, stdlib,
     stream::{self, *},
@@ -10,9 +11,9 @@ use std::collections::VecDeque;
 use std::sync::{RwLockReadGuard, RwLockWriteGuard};
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{Debug, Display, Formatter},
-    sync::Arc,
+    sync::{Arc, Weak},
     vec,
 };
 use std::{env::var, mem, path::Path};
@@ -23,7 +24,7 @@ pub type AFunc = Arc<Func>;
 pub type OError = Result<(), Error>;
 
 thread_local! {
-    static RUNTIME: RefCell<Option<Arc<Mut<Runtime>>>> = RefCell::new(None);
+    static RUNTIME: RefCell<Option<Arc<Mut<Runtime>>>> = const { RefCell::new(None) };
 }
 
 /// Obtains a reference to the runtime.
@@ -56,6 +57,8 @@ pub fn get_type(name: &str) -> Option<AMType> {
 /// - types
 /// - type refs
 /// - streams
+/// - running generators
+/// - whether functions' return values are checked against their signatures
 #[derive(Clone)]
 pub struct Runtime {
     next_type_id: u32,
@@ -63,8 +66,13 @@ pub struct Runtime {
     types_by_id: HashMap<u32, AMType>,
     next_stream_id: u128,
     streams: HashMap<u128, Arc<Mut<Stream>>>,
+    /// The threads of generators that were started and haven't ended yet.
+    generators: Vec<Weak<generator::GeneratorThread>>,
     pub embedded_files: HashMap<&'static str, &'static str>,
     pub native_functions: HashMap<&'static str, (u32, FuncImpl)>,
+    /// Set by `SPL_STRICT`. When enabled, values returned by SPL functions must match their
+    /// declared types, or InvalidType is thrown.
+    pub strict: bool,
 }
 
 impl Debug for Runtime {
@@ -92,8 +100,10 @@ impl Runtime {
             types_by_id: HashMap::new(),
             next_stream_id: 0,
             streams: HashMap::new(),
+            generators: Vec::new(),
             embedded_files: HashMap::new(),
             native_functions: HashMap::new(),
+            strict: var("SPL_STRICT").is_ok(),
         };
         let _ = rt.make_type("null".to_owned(), Ok); // infallible
         let _ = rt.make_type("int".to_owned(), Ok); // infallible
@@ -104,6 +114,10 @@ impl Runtime {
         let _ = rt.make_type("func".to_owned(), Ok); // infallible
         let _ = rt.make_type("array".to_owned(), Ok); // infallible
         let _ = rt.make_type("str".to_owned(), Ok); // infallible
+        let _ = rt.make_type("bool".to_owned(), Ok); // infallible
+        let _ = rt.make_type("bytes".to_owned(), Ok); // infallible
+        let _ = rt.make_type("bigint".to_owned(), Ok); // infallible
+        let _ = rt.make_type("decimal".to_owned(), Ok); // infallible
         stdlib::register(&mut rt);
         rt
     }
@@ -131,12 +145,73 @@ impl Runtime {
             parents: Vec::new(),
             functions: HashMap::new(),
             properties: Vec::new(),
+            defaults: Vec::new(),
+            mro: Vec::new(),
+            is_interface: false,
+            requires: Vec::new(),
         })?));
         self.types_by_id.insert(self.next_type_id - 1, t.clone());
         self.types_by_name.insert(name, t.clone());
         Ok(t)
     }
 
+    /// Adds `parent` as a parent type of `child`, updating the method resolution order of `child`
+    /// and of all types including it. Fails without changing anything if this would make a type
+    /// include itself, if there is no consistent order, or if a type that isn't an interface
+    /// would include an interface without defining the methods it requires.
+    pub fn include(&mut self, parent: AMType, child: AMType) -> Result<(), String> {
+        if child.lock_ro().parents.contains(&parent) {
+            return Ok(());
+        }
+        let names = format!(
+            "{} in {}",
+            parent.lock_ro().get_name(),
+            child.lock_ro().get_name()
+        );
+        if parent == child || parent.lock_ro().mro.contains(&child) {
+            return Err(format!("cannot include {names}: it would include itself"));
+        }
+        // a type's ancestors have shorter orders than it, so they are updated before it.
+        let mut affected: Vec<AMType> = self
+            .types_by_id
+            .values()
+            .filter(|t| t.lock_ro().mro.contains(&child))
+            .cloned()
+            .collect();
+        affected.sort_by_key(|t| t.lock_ro().mro.len());
+        affected.insert(0, child.clone());
+        let old: Vec<_> = affected.iter().map(|t| t.lock_ro().get_mro()).collect();
+        child.lock().parents.push(parent);
+        let mut error = None;
+        for t in &affected {
+            let mro = linearize(&t.lock_ro().parents);
+            match mro {
+                Some(mro) => t.lock().mro = mro,
+                None => {
+                    error = Some("no consistent method resolution order".to_owned());
+                    break;
+                }
+            }
+        }
+        if error.is_none() {
+            error = affected.iter().find_map(|t| {
+                let t = t.lock_ro();
+                if t.is_interface {
+                    return None;
+                }
+                t.mro.iter().find_map(|x| t.missing(&x.lock_ro()))
+            });
+        }
+        if let Some(error) = error {
+            child.lock().parents.pop();
+            for (t, mro) in affected.iter().zip(old) {
+                t.lock().mro = mro;
+            }
+            return Err(format!("cannot include {names}: {error}"));
+        }
+        Ok(())
+    }
+
     pub fn register_stream(&mut self, stream: Stream) -> (u128, Arc<Mut<Stream>>) {
         let id = (self.next_stream_id, self.next_stream_id += 1).0;
         self.streams.insert(id, Arc::new(Mut::new(stream)));
@@ -151,6 +226,12 @@ impl Runtime {
         self.streams.remove(&id);
     }
 
+    /// Keeps track of the thread of a generator, to stop it when the runtime is reset.
+    pub fn register_generator(&mut self, thread: Weak<generator::GeneratorThread>) {
+        self.generators.retain(|x| x.strong_count() != 0);
+        self.generators.push(thread);
+    }
+
     pub fn load_native_function(&self, name: &str) -> &(u32, FuncImpl) {
         self.native_functions.get(name).unwrap_or_else(|| {
             panic!(
@@ -159,8 +240,18 @@ impl Runtime {
         })
     }
 
+    /// Unsets the runtime of this thread, stopping the generators that are waiting to be resumed.
     pub fn reset() {
-        RUNTIME.with(|x| *x.borrow_mut() = None);
+        if let Some(rt) = RUNTIME.with(|x| x.borrow_mut().take()) {
+            // the lock is released first, as the threads may use the runtime while stopping.
+            let generators = mem::take(&mut rt.lock().generators);
+            generator::stop_all(generators);
+        }
+    }
+
+    /// Gets the runtime set on this thread, for example to .set() it on another thread.
+    pub fn get() -> Arc<Mut<Runtime>> {
+        RUNTIME.with(|x| x.borrow().clone().expect("no runtime (use .set())"))
     }
 }
 
@@ -194,6 +285,7 @@ pub struct FrameInfo {
 /// This holds:
 /// - its parent
 /// - variables
+/// - which of its variables are constants
 /// - functions
 /// - its origin ([FrameInfo])
 /// - whether all functions in it should be made global.
@@ -201,6 +293,7 @@ pub struct FrameInfo {
 pub struct Frame {
     parent: Option<Arc<Frame>>,
     pub variables: Mut<HashMap<String, AMObject>>,
+    pub constants: Mut<HashSet<String>>,
     pub functions: Mut<HashMap<String, AFunc>>,
     pub origin: FrameInfo,
     pub redirect_to_base: bool,
@@ -232,6 +325,7 @@ impl Frame {
         Frame {
             parent: None,
             variables: Mut::new(HashMap::new()),
+            constants: Mut::new(HashSet::new()),
             functions: Mut::new(HashMap::new()),
             origin: FrameInfo {
                 file: "\0".to_owned(),
@@ -245,6 +339,7 @@ impl Frame {
         Frame {
             parent: None,
             variables: Mut::new(HashMap::new()),
+            constants: Mut::new(HashSet::new()),
             functions: Mut::new(HashMap::new()),
             origin: FrameInfo {
                 file: "std.spl".to_owned(),
@@ -258,6 +353,7 @@ impl Frame {
         Frame {
             parent: None,
             variables: Mut::new(HashMap::new()),
+            constants: Mut::new(HashSet::new()),
             functions: Mut::new(HashMap::new()),
             origin: info,
             redirect_to_base: false,
@@ -267,6 +363,7 @@ impl Frame {
     pub fn new(parent: Arc<Frame>, function: String) -> Self {
         Frame {
             variables: Mut::new(HashMap::new()),
+            constants: Mut::new(HashSet::new()),
             functions: Mut::new(HashMap::new()),
             origin: FrameInfo {
                 function,
@@ -286,6 +383,7 @@ impl Frame {
         Frame {
             parent: Some(parent),
             variables: Mut::new(HashMap::new()),
+            constants: Mut::new(HashSet::new()),
             functions: Mut::new(HashMap::new()),
             origin: FrameInfo {
                 file: origin,
@@ -299,6 +397,9 @@ impl Frame {
         let mut frame = self;
         loop {
             if let Some(x) = frame.variables.lock().get_mut(&name) {
+                if frame.constants.lock_ro().contains(&name) {
+                    return Err(stack.error(ErrorKind::ReadOnly(name)));
+                }
                 *x = obj;
                 return Ok(());
             }
@@ -367,6 +468,7 @@ impl Frame {
 /// - a stack of frames
 /// - the main stack of objects
 /// - a return accumultor: how many blocks to return directly from
+/// - the break, continue or return currently leaving blocks, if any
 #[derive(Clone, Debug)]
 pub struct Stack {
     frames: Vec<Arc<Frame>>,
@@ -374,6 +476,18 @@ pub struct Stack {
     objcall_stack: Vec<AMObject>,
     files: Vec<String>,
     pub return_accumultor: u32,
+    pub jump: Option<Jump>,
+}
+
+/// Where a break, continue or return keyword is jumping to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Jump {
+    /// Out of the innermost loop.
+    Break,
+    /// To the next iteration of the innermost loop.
+    Continue,
+    /// Out of the current function.
+    Return,
 }
 
 impl Display for Stack {
@@ -410,11 +524,13 @@ impl Stack {
             objcall_stack: Vec::new(),
             files: Vec::new(),
             return_accumultor: 0,
+            jump: None,
         };
 
         dyn_fns::register(&mut r, o.clone());
         std_fns::register(&mut r, o.clone());
-        stream::register(&mut r, o);
+        stream::register(&mut r, o.clone());
+        generator::register(&mut r, o);
 
         r
     }
@@ -427,11 +543,13 @@ impl Stack {
             objcall_stack: Vec::new(),
             files: Vec::new(),
             return_accumultor: 0,
+            jump: None,
         };
 
         dyn_fns::register(&mut r, o.clone());
         std_fns::register(&mut r, o.clone());
-        stream::register(&mut r, o);
+        stream::register(&mut r, o.clone());
+        generator::register(&mut r, o);
 
         r
     }
@@ -458,7 +576,154 @@ impl Stack {
         self.frames.push(Arc::new(f));
         let r = func.to_call.call(self);
         self.frames.pop().unwrap();
-        r
+        r?;
+        if let Some(ref signature) = func.signature {
+            if runtime(|rt| rt.strict) {
+                signature.check(self, &func.name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls the `debug-str` method of an object, if its type has one.
+    fn debug_hook(&mut self, object: &AMObject) -> Result<Option<String>, Error> {
+        let Some(f) = object
+            .lock_ro()
+            .kind
+            .lock_ro()
+            .get_fn("debug-str".to_owned())
+        else {
+            return Ok(None);
+        };
+        self.push(object.clone());
+        self.call(&f)?;
+        let result = self.pop();
+        let result = result.lock_ro();
+        match result.native {
+            Value::Str(ref s) => Ok(Some(s.clone())),
+            _ => Err(self.error(ErrorKind::InvalidType(
+                result.kind.lock_ro().name.clone(),
+                "str (returned by debug-str)".to_owned(),
+            ))),
+        }
+    }
+
+    /// The type defining the method being run. Lambdas belong to the method they are written in.
+    fn method_type(&self) -> Option<AMType> {
+        let mut frame = Some(self.get_frame());
+        while let Some(f) = frame {
+            if let Some((name, _)) = f.origin.function.rsplit_once(':') {
+                if let Some(t) = runtime(|rt| rt.get_type_by_name(name)) {
+                    return Some(t);
+                }
+            }
+            frame = f.parent.clone();
+        }
+        None
+    }
+
+    /// Makes an object an instance of `kind`. Fields it doesn't have yet are set to their default
+    /// value, or to null if they have none.
+    pub fn set_type(&mut self, object: &AMObject, kind: AMType) -> OError {
+        let defaults: Vec<_> = {
+            let o = object.lock_ro();
+            kind.lock_ro()
+                .get_defaults()
+                .into_iter()
+                .filter(|(name, _)| !o.property_map.contains_key(name))
+                .collect()
+        };
+        let mut o = object.lock();
+        o.check_mutable(self)?;
+        kind.lock_ro().write_into(&mut o);
+        o.kind = kind.clone();
+        mem::drop(o);
+        for (name, f) in defaults {
+            self.call(&f)?;
+            let setter = kind.lock_ro().get_fn("=".to_owned() + &name);
+            if let Some(setter) = setter {
+                self.push(object.clone());
+                self.call(&setter)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The representation of an object in dumps: the result of its `debug-str` method if its type
+    /// has one, or that of [Object::write_debug], which uses `debug-str` for nested objects.
+    pub fn debug_str(&mut self, object: &AMObject) -> Result<String, Error> {
+        if let Some(s) = self.debug_hook(object)? {
+            return Ok(s);
+        }
+        let mut output = String::new();
+        object
+            .lock_ro()
+            .write_debug(&mut output, 0, &mut Vec::new(), &mut |x| self.debug_hook(x))?;
+        Ok(output)
+    }
+
+    /// Like the [Display] implementation, but with objects written using [Stack::debug_str].
+    pub fn dump(&mut self) -> Result<String, Error> {
+        let mut output = String::new();
+        for frame in self.frames.clone() {
+            output += "Frame:";
+            output += &frame.readable_path();
+            output += "\n\nVars: \n";
+            let variables: Vec<_> = frame
+                .variables
+                .lock_ro()
+                .iter()
+                .map(|(name, object)| (name.clone(), object.clone()))
+                .collect();
+            for (name, object) in variables {
+                output += "  ";
+                output += &name;
+                output += ": ";
+                output += &self.debug_str(&object)?.replace('\n', "\n  ");
+                output += "\n";
+            }
+            output += "\nFuncs: \n";
+            for name in frame.functions.lock_ro().keys() {
+                output += "  ";
+                output += name;
+                output += "\n";
+            }
+            output += "\n\n\n\n";
+        }
+        output += "Stack: \n";
+        for object in self.object_stack.clone() {
+            output += "  ";
+            output += &self.debug_str(&object)?.replace('\n', "\n  ");
+            output += "\n";
+        }
+        Ok(output)
+    }
+
+    /// Handles a jump at the end of a loop iteration, returning whether the loop should be left.
+    fn leave_loop(&mut self) -> bool {
+        match self.jump {
+            Some(Jump::Break) => {
+                self.jump = None;
+                true
+            }
+            Some(Jump::Continue) => {
+                self.jump = None;
+                false
+            }
+            Some(Jump::Return) => true,
+            None => false,
+        }
+    }
+
+    pub fn get_method(&self, object: &AMObject, name: &str) -> Result<AFunc, Error> {
+        let kind = object.lock_ro().kind.clone();
+        let kind = kind.lock_ro();
+        kind.get_fn(name.to_owned()).ok_or_else(|| {
+            self.error(ErrorKind::MethodNotFound(
+                kind.name.clone(),
+                name.to_owned(),
+            ))
+        })
     }
 
     pub fn get_func(&self, name: String) -> Result<AFunc, Error> {
@@ -476,39 +741,61 @@ impl Stack {
         }
     }
 
-    pub fn define_var(&mut self, name: String) {
-        let mut frame = self.frames.last().unwrap().clone();
+    /// The frame variables are defined in: the current one, or the base frame if it redirects
+    /// there.
+    fn var_frame(&self) -> Arc<Frame> {
+        let frame = self.frames.last().unwrap();
         if frame.redirect_to_base {
-            frame = self.frames.first().unwrap().clone();
+            self.frames.first().unwrap().clone()
+        } else {
+            frame.clone()
         }
+    }
+
+    /// Defines a variable, unless it is already a constant in the frame it would be defined in.
+    pub fn define_var(&mut self, name: String) {
+        let frame = self.var_frame();
+        if frame.constants.lock_ro().contains(&name) {
+            return;
+        }
+        // the accessors only refer to the frame weakly, as they are kept in it.
+        let origin = Arc::new(Frame::dummy());
         let tmpname = name.clone();
-        let tmpframe = frame.clone();
+        let tmpframe = Arc::downgrade(&frame);
         frame.functions.lock().insert(
             name.clone(),
             Arc::new(Func {
                 ret_count: 1,
-                origin: frame.clone(),
+                origin: origin.clone(),
                 to_call: FuncImpl::NativeDyn(Arc::new(Box::new(move |stack| {
-                    stack.push(tmpframe.get_var(tmpname.clone(), stack)?);
+                    let Some(frame) = tmpframe.upgrade() else {
+                        return stack.err(ErrorKind::VariableNotFound(tmpname.clone()));
+                    };
+                    stack.push(frame.get_var(tmpname.clone(), stack)?);
                     Ok(())
                 }))),
                 run_as_base: false,
+                signature: None,
                 fname: Some("RUNTIME".to_owned()),
                 name: name.clone(),
             }),
         );
         let tmpname = name.clone();
-        let tmpframe = frame.clone();
+        let tmpframe = Arc::downgrade(&frame);
         frame.functions.lock().insert(
             "=".to_owned() + &name,
             Arc::new(Func {
                 ret_count: 0,
-                origin: frame.clone(),
+                origin,
                 to_call: FuncImpl::NativeDyn(Arc::new(Box::new(move |stack| {
                     let v = stack.pop();
-                    tmpframe.set_var(tmpname.clone(), v, stack)
+                    let Some(frame) = tmpframe.upgrade() else {
+                        return stack.err(ErrorKind::VariableNotFound(tmpname.clone()));
+                    };
+                    frame.set_var(tmpname.clone(), v, stack)
                 }))),
                 run_as_base: false,
+                signature: None,
                 fname: Some("RUNTIME".to_owned()),
                 name: "=".to_owned() + &name,
             }),
@@ -516,6 +803,25 @@ impl Stack {
         frame.variables.lock().insert(name, Value::Null.spl());
     }
 
+    /// Defines a variable holding the value, which can not be reassigned afterwards. Fails with
+    /// ReadOnly if it already is a constant.
+    /// Defines a constant. Defining it again in the same frame with an equal value does nothing,
+    /// so code running as base more than once, like a file read with `dyn-readf`, can define
+    /// constants.
+    pub fn define_const(&mut self, name: String, value: AMObject) -> OError {
+        let frame = self.var_frame();
+        if frame.constants.lock_ro().contains(&name) {
+            let existing = frame.variables.lock_ro().get(&name).cloned();
+            if existing.is_some_and(|x| *x.lock_ro() == *value.lock_ro()) {
+                return Ok(());
+            }
+        }
+        self.define_var(name.clone());
+        self.set_var(name.clone(), value)?;
+        self.var_frame().constants.lock().insert(name);
+        Ok(())
+    }
+
     pub fn pop_until(&mut self, obj: AMObject) -> Vec<AMObject> {
         let Some((idx, ..)) = self.object_stack.iter().enumerate().rfind(|o| *o.1.lock_ro() == *obj.lock_ro()) else {
             return Vec::new()
@@ -640,18 +946,24 @@ pub enum Keyword {
     /// Defines a variable.
     /// equivalent to "<name>" dyn-def
     Def(String),
+    /// const <name> <value>
+    ///
+    /// Defines a variable holding <value>, a single word, that can not be reassigned. The
+    /// variable exists while <value> runs, so `const null null` works.
+    /// not available through dyn
+    Const(String, Words),
     /// func <name> { <rem> | <words> }
     ///
-    /// Defines function <name> with <rem> return size
+    /// Defines function <name> returning <rem>
     /// equivalent to { <rem> | <words> } "<name>" dyn-func
-    Func(String, u32, Words),
+    Func(String, Signature, Words),
     /// construct <name> { <field> <...> ; <fn-name> { <rem> | <words> } <...> }
     ///
-    /// Creates type <name>
+    /// Creates type <name>. A field can be written as <field>: <type> = <default>, see [Field].
     /// equivalent to
     /// "<name>" dyn-construct; "<field>" "<name>" dyn-def-field { <rem> | <words> } "<fn-name>"
     /// "<name>" dyn-def-method
-    Construct(String, Vec<String>, Vec<(String, (u32, Words))>, bool),
+    Construct(String, Vec<Field>, Vec<(String, (Signature, Words))>, bool),
     /// include <typeA> in <typeB>
     ///
     /// Adds <typeA> as a parent type of <typeB>.
@@ -672,18 +984,87 @@ pub enum Keyword {
     /// If wordsA result in a truthy value being on the top of the stack, execute wordsB.
     /// equivalent to { | <wordsB> } dyn-if
     If(Words),
+    /// if { <wordsB> } else { <wordsC> }
+    ///
+    /// If the top of the stack is truthy, execute wordsB, otherwise execute wordsC.
+    /// `elif { <wordsD> } { <wordsE> }` can be used in place of the else block, and is short for
+    /// `else { <wordsD> if { <wordsE> } }`. It can be followed by more elif blocks or an else block.
+    /// equivalent to { | <wordsB> } { | <wordsC> } dyn-if-else
+    IfElse(Words, Words),
+    /// match { <pattern> { <wordsB> } <...> [_ { <wordsD> }] }
+    ///
+    /// Pops the top of the stack and checks it against each [Pattern] in order. The wordsB of the
+    /// first one it matches are executed. If there is none, wordsD are executed, if given.
+    /// not available through dyn
+    Match(Vec<(Pattern, Words)>, Option<Words>),
+    /// for <item> in <wordsA> { <wordsB> }
+    ///
+    /// Executes wordsA, and then wordsB for each item of the resulting iterator, which is stored in
+    /// the variable <item>. If the result of wordsA has no `next` method, its `iter` method is
+    /// called to get the iterator. wordsA can not contain blocks, use a variable instead.
+    /// not available through dyn
+    For(String, Words, Words),
+    /// break
+    ///
+    /// Leaves the innermost while or for loop.
+    /// not available through dyn
+    Break,
+    /// continue
+    ///
+    /// Skips to the next iteration of the innermost while or for loop.
+    /// not available through dyn
+    Continue,
+    /// return
+    ///
+    /// Returns from the current function, leaving the stack as it is.
+    /// not available through dyn
+    Return,
+    /// interface <name> { <required> <...> ; <fn-name> { <rem> | <words> } <...> }
+    ///
+    /// Creates type <name> like construct, but without fields or a constructor. Every <required>
+    /// is a method name, optionally followed by a signature { <rem> | }, which types including
+    /// <name> must define.
+    /// not available through dyn
+    Interface(
+        String,
+        Vec<(String, Option<Signature>)>,
+        Vec<(String, (Signature, Words))>,
+    ),
+    /// enum <name> { <variant>[(<field> <...>)] <...> ; <fn-name> { <rem> | <words> } <...> }
+    ///
+    /// Creates type <name> with the given methods, and a type <name>:<variant> including it for
+    /// every variant. The variable <name> holds an instance of <name>, where <name>:<variant>
+    /// returns "<name>:<variant>", so <name>:<variant>:new creates a variant, taking its fields
+    /// in order. <variant> <value>:is-variant? checks if a value is that variant.
+    /// not available through dyn
+    Enum(
+        String,
+        Vec<(String, Vec<Field>)>,
+        Vec<(String, (Signature, Words))>,
+    ),
+    /// super:<name>
+    ///
+    /// Calls method <name> of the object on the stack, skipping the types in its method
+    /// resolution order up to and including the one defining the method being run. Like other
+    /// method calls, it can be followed by ; to discard the result.
+    /// not available through dyn
+    Super(String, bool),
     /// with <item> <...> ;
     ///
-    /// Defines variables in reverse order.
+    /// Defines variables in reverse order. Items can also be [Binding] patterns taking apart
+    /// arrays and constructs.
     /// equivalent to def <...> =<...> def <item> =<item>
     /// or "<...>" dyn-def "=<...>" dyn-call "<item>" dyn-def "=<item>" dyn-call
-    With(Vec<String>),
-    /// catch [<type> <...>] { <code> } with { <wordsOnCatch> }
+    With(Vec<Binding>),
+    /// catch [<type> <...>] { <code> } with { <wordsOnCatch> } [finally { <wordsFinally> }]
     ///
     /// Catches errors that happen within <code>, running <wordsOnCatch> when an error is
-    /// encountered and the error is of <type> (or, if no type is specified, any error).
-    /// equivalent to \[ ["<type>" <...>] \] { | <code> } { | <wordsOnCatch> } dyn-catch
-    Catch(Vec<String>, Words, Words),
+    /// encountered and the error is of <type> (or, if no type is specified, any error). A type is
+    /// either the name of an [ErrorKind], or that of a construct thrown as object or included by
+    /// it. <wordsFinally> are run afterwards, even if <code> or <wordsOnCatch> throw or return.
+    /// equivalent to \[ ["<type>" <...>] \] { | <code> } { | <wordsOnCatch> } dyn-catch, without
+    /// finally
+    Catch(Vec<String>, Words, Words, Option<Words>),
     /// <none>
     ///
     /// Used by `object:method <{ arg1 arg2 }` syntax. Generates as:
@@ -705,6 +1086,220 @@ pub enum Keyword {
     FuncOf(String, String, FuncImplType),
 }
 
+/// A pattern of a match arm.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// <words>, matching values equal to their result, like eq.
+    Value(Words),
+    /// <words>(<name> <...>), matching instances of the type named by the result of words, or of
+    /// types including it. Its fields are assigned to the names, in the order they were declared
+    /// in, defining them as variables. Fields named `_` are skipped.
+    Destructure(Words, Vec<String>),
+}
+
+impl Pattern {
+    /// The words producing the value or type name to match against.
+    pub fn words(&self) -> &Words {
+        match self {
+            Pattern::Value(words) | Pattern::Destructure(words, _) => words,
+        }
+    }
+
+    /// Checks if the value matches, defining the bound variables if it does.
+    pub fn matches(&self, value: &AMObject, stack: &mut Stack) -> Result<bool, Error> {
+        match self {
+            Pattern::Value(words) => {
+                stack.push(value.clone());
+                words.exec(stack)?;
+                std_fns::eq(stack)?;
+                Ok(stack.pop().lock_ro().is_truthy())
+            }
+            Pattern::Destructure(words, names) => {
+                words.exec(stack)?;
+                let Value::Str(name) = stack.pop().lock_ro().native.clone() else {
+                    return Err(stack.error(ErrorKind::InvalidCall("match".to_owned())));
+                };
+                let t = runtime(|rt| rt.get_type_by_name(&name))
+                    .ok_or_else(|| stack.error(ErrorKind::TypeNotFound(name.clone())))?;
+                // a pattern with too many names is wrong no matter what it is matched against.
+                let properties = t.lock_ro().properties.clone();
+                if names.len() > properties.len() {
+                    return Err(stack.error(ErrorKind::PropertyNotFound(
+                        name,
+                        names[properties.len()].clone(),
+                    )));
+                }
+                let kind = value.lock_ro().kind.clone();
+                if kind != t && !kind.lock_ro().get_mro().contains(&t) {
+                    return Ok(false);
+                }
+                for (var, property) in names.iter().zip(properties) {
+                    if var == "_" {
+                        continue;
+                    }
+                    let o = value.lock_ro().field(&property, stack)?;
+                    stack.define_var(var.clone());
+                    stack.set_var(var.clone(), o)?;
+                }
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// What `with` assigns a value to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Binding {
+    /// <name>, the value itself. Inside of patterns, `_` skips the value.
+    Name(String),
+    /// [<binding> <...> ..<rest>], the items of an array, which must have one item per binding.
+    /// With ..<rest>, it can have more, which are put into a new array assigned to <rest>.
+    Array(Vec<Binding>, Option<String>),
+    /// {<field> <...>}, fields of a construct, each assigned to a variable of the same name.
+    Fields(Vec<String>),
+}
+
+impl Binding {
+    /// The names of the variables this defines.
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Binding::Name(name) => vec![name.clone()],
+            Binding::Array(items, rest) => items
+                .iter()
+                .filter(|x| **x != Binding::Name("_".to_owned()))
+                .flat_map(Binding::names)
+                .chain(rest.iter().filter(|x| *x != "_").cloned())
+                .collect(),
+            Binding::Fields(names) => names.clone(),
+        }
+    }
+
+    /// Defines the variables of this binding and assigns the value, or the parts of it the
+    /// pattern takes, to them. Fails with a PatternMismatch if the value doesn't fit the pattern.
+    pub fn bind(&self, value: AMObject, stack: &mut Stack) -> OError {
+        let assign = |name: &String, value: AMObject, stack: &mut Stack| {
+            stack.define_var(name.clone());
+            stack.set_var(name.clone(), value)
+        };
+        match self {
+            Binding::Name(name) => assign(name, value, stack),
+            Binding::Array(items, rest) => {
+                let array = match value.lock_ro().native {
+                    Value::Array(ref array) => array.clone(),
+                    _ => {
+                        return stack.err(ErrorKind::PatternMismatch(
+                            self.to_string(),
+                            value.lock_ro().kind.lock_ro().get_name(),
+                        ))
+                    }
+                };
+                if array.len() < items.len() || (rest.is_none() && array.len() > items.len()) {
+                    return stack.err(ErrorKind::PatternMismatch(
+                        self.to_string(),
+                        format!("array of {} items", array.len()),
+                    ));
+                }
+                for (item, binding) in array.iter().zip(items) {
+                    if *binding != Binding::Name("_".to_owned()) {
+                        binding.bind(item.clone(), stack)?;
+                    }
+                }
+                match rest {
+                    Some(rest) if rest != "_" => assign(
+                        rest,
+                        Value::Array(array[items.len()..].to_vec()).spl(),
+                        stack,
+                    ),
+                    _ => Ok(()),
+                }
+            }
+            Binding::Fields(names) => {
+                for name in names {
+                    let o = value.lock_ro();
+                    let Some(field) = o.property_map.get(name).cloned() else {
+                        return stack.err(ErrorKind::PatternMismatch(
+                            self.to_string(),
+                            o.kind.lock_ro().get_name(),
+                        ));
+                    };
+                    mem::drop(o);
+                    assign(name, field, stack)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Name(name) => f.write_str(name),
+            Binding::Array(items, rest) => {
+                let mut parts: Vec<_> = items.iter().map(ToString::to_string).collect();
+                parts.extend(rest.iter().map(|x| format!("..{x}")));
+                write!(f, "[{}]", parts.join(" "))
+            }
+            Binding::Fields(names) => write!(f, "{{{}}}", names.join(" ")),
+        }
+    }
+}
+
+/// A field of a construct, `<name>`, optionally followed by `: <type>` and `= <default>`.
+///
+/// Assigning a value that doesn't match the type to the field is an error. The default is a
+/// single word or a block, evaluated for every new instance. Fields without one start out as
+/// null, so their type is effectively `<type>|null` until they are first assigned.
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub name: String,
+    pub kind: TypeSpec,
+    pub default: Option<Words>,
+}
+
+impl Field {
+    /// A field of any type without a default.
+    pub fn new(name: String) -> Field {
+        Field {
+            name,
+            kind: TypeSpec::Any,
+            default: None,
+        }
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if self.kind != TypeSpec::Any {
+            write!(f, ": {}", self.kind)?;
+        }
+        Ok(())
+    }
+}
+
+impl Keyword {
+    /// The blocks of code this keyword runs in place, not including function bodies.
+    pub fn blocks(&self) -> Vec<&Words> {
+        match self {
+            Keyword::While(cond, blk) => vec![cond, blk],
+            Keyword::If(blk) => vec![blk],
+            Keyword::IfElse(blk, els) => vec![blk, els],
+            Keyword::Match(arms, default) => arms
+                .iter()
+                .flat_map(|(pattern, blk)| [pattern.words(), blk])
+                .chain(default)
+                .collect(),
+            Keyword::Const(_, value) => vec![value],
+            Keyword::For(_, iterable, blk) => vec![iterable, blk],
+            Keyword::Catch(_, blk, ctch, finally) => {
+                [blk, ctch].into_iter().chain(finally).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
 /// Any SPL value that is not a construct.
 ///
 /// Holds its rust representation.
@@ -719,6 +1314,10 @@ pub enum Value {
     Func(AFunc),
     Array(Vec<AMObject>),
     Str(String),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    BigInt(BigInt),
+    Decimal(Decimal),
 }
 
 impl Value {
@@ -745,7 +1344,13 @@ impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
             (Value::Mega(a), Value::Mega(b)) => a.partial_cmp(b),
-            _ => panic!(),
+            (Value::BigInt(a), Value::BigInt(b)) => a.partial_cmp(b),
+            (Value::Decimal(a), Value::Decimal(b)) => a.partial_cmp(b),
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            (Value::Long(a), Value::Long(b)) => a.partial_cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b),
+            _ => None,
         }
     }
 }
@@ -788,7 +1393,16 @@ impl FuncImpl {
         match self {
             FuncImpl::Native(x) => x(stack),
             FuncImpl::NativeDyn(x) => x(stack),
-            FuncImpl::SPL(x) => x.exec(stack),
+            FuncImpl::SPL(x) => {
+                x.exec(stack)?;
+                match stack.jump.take() {
+                    Some(Jump::Break) => stack.err(ErrorKind::InvalidCall("break".to_owned())),
+                    Some(Jump::Continue) => {
+                        stack.err(ErrorKind::InvalidCall("continue".to_owned()))
+                    }
+                    _ => Ok(()),
+                }
+            }
         }
     }
 }
@@ -803,6 +1417,7 @@ impl FuncImpl {
 /// - the name of the function.
 /// - wether it should be run as the root layer (= wether functions it defines should be made
 ///   global)
+/// - the declared return types, if it was written in SPL
 #[derive(Clone)]
 pub struct Func {
     pub ret_count: u32,
@@ -811,6 +1426,7 @@ pub struct Func {
     pub fname: Option<String>,
     pub name: String,
     pub run_as_base: bool,
+    pub signature: Option<Signature>,
 }
 
 impl PartialEq for Func {
@@ -826,6 +1442,360 @@ impl Debug for Func {
     }
 }
 
+/// Splits `s` at every `sep` that isn't inside of brackets.
+pub(crate) fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut depth = 0;
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            c if c == sep && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// A type in a function signature, like `mega`, `[int]`, `str|null` or `..`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeSpec {
+    /// `any`, matches everything.
+    Any,
+    /// `..` or `...`, any amount of values of any type.
+    Rest,
+    /// A type name. Names that aren't types (like `item` or `this`) are only documentation and
+    /// match everything.
+    Named(String),
+    /// `[<type>]`, an array of which all elements match <type>.
+    Array(Box<TypeSpec>),
+    /// `[<type>,<type>]`, an array with exactly these elements.
+    Tuple(Vec<TypeSpec>),
+    /// `<type>|<type>`, matches if any of the types do.
+    Union(Vec<TypeSpec>),
+}
+
+impl TypeSpec {
+    pub fn parse(s: &str) -> TypeSpec {
+        let parts = split_top_level(s, '|');
+        if parts.len() > 1 {
+            return TypeSpec::Union(parts.into_iter().map(TypeSpec::parse).collect());
+        }
+        match s {
+            "any" | "" => TypeSpec::Any,
+            ".." | "..." => TypeSpec::Rest,
+            x if x.starts_with('[') && x.ends_with(']') => {
+                let parts = split_top_level(&x[1..x.len() - 1], ',');
+                if parts.len() > 1 {
+                    TypeSpec::Tuple(parts.into_iter().map(TypeSpec::parse).collect())
+                } else {
+                    TypeSpec::Array(Box::new(TypeSpec::parse(parts[0])))
+                }
+            }
+            x => TypeSpec::Named(x.to_owned()),
+        }
+    }
+
+    pub fn matches(&self, obj: &AMObject) -> bool {
+        match self {
+            TypeSpec::Any | TypeSpec::Rest => true,
+            TypeSpec::Named(name) => {
+                let Some(t) = runtime(|rt| rt.get_type_by_name(name)) else {
+                    return true;
+                };
+                let kind = obj.lock_ro().kind.clone();
+                if kind == t {
+                    return true;
+                }
+                let kind = kind.lock_ro();
+                kind.mro.contains(&t)
+            }
+            TypeSpec::Array(spec) => match obj.lock_ro().native {
+                Value::Array(ref a) => a.iter().all(|x| spec.matches(x)),
+                _ => false,
+            },
+            TypeSpec::Tuple(specs) => match obj.lock_ro().native {
+                Value::Array(ref a) => {
+                    a.len() == specs.len() && specs.iter().zip(a).all(|(s, x)| s.matches(x))
+                }
+                _ => false,
+            },
+            TypeSpec::Union(specs) => specs.iter().any(|x| x.matches(obj)),
+        }
+    }
+}
+
+impl Display for TypeSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeSpec::Any => f.write_str("any"),
+            TypeSpec::Rest => f.write_str(".."),
+            TypeSpec::Named(name) => f.write_str(name),
+            TypeSpec::Array(spec) => write!(f, "[{spec}]"),
+            TypeSpec::Tuple(specs) => write!(
+                f,
+                "[{}]",
+                specs
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            TypeSpec::Union(specs) => f.write_str(
+                &specs
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("|"),
+            ),
+        }
+    }
+}
+
+/// The declared return types of a function, the part before the `|` in `{ mega [int] | ... }`.
+///
+/// ```
+/// use spl::runtime::{Signature, TypeSpec};
+/// let signature = Signature::parse(&["str|null", "[int]"]);
+/// assert_eq!(signature.ret_count(), 2);
+/// assert_eq!(signature.returns[1], TypeSpec::Array(Box::new(TypeSpec::Named("int".to_owned()))));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signature {
+    pub returns: Vec<TypeSpec>,
+}
+
+impl Signature {
+    pub fn parse(words: &[impl AsRef<str>]) -> Signature {
+        Signature {
+            returns: words.iter().map(|x| TypeSpec::parse(x.as_ref())).collect(),
+        }
+    }
+
+    /// A signature of `ret_count` values of any type.
+    pub fn any(ret_count: u32) -> Signature {
+        Signature {
+            returns: vec![TypeSpec::Any; ret_count as usize],
+        }
+    }
+
+    pub fn ret_count(&self) -> u32 {
+        self.returns.len() as u32
+    }
+
+    /// Checks the values on top of the stack against this signature, after a function named
+    /// `name` returned them.
+    pub fn check(&self, stack: &Stack, name: &str) -> OError {
+        if self.returns.contains(&TypeSpec::Rest) {
+            return Ok(());
+        }
+        let values =
+            &stack.object_stack[stack.object_stack.len().saturating_sub(self.returns.len())..];
+        let missing = self.returns.len() - values.len();
+        for (i, spec) in self.returns.iter().enumerate() {
+            let found = match i.checked_sub(missing) {
+                Some(i) if spec.matches(&values[i]) => continue,
+                Some(i) => values[i].lock_ro().kind.lock_ro().get_name(),
+                None => "nothing".to_owned(),
+            };
+            return stack.err(ErrorKind::InvalidType(
+                found,
+                format!("{spec} (returned by {name})"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            &self
+                .returns
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+}
+
+/// How a value is aligned within the width of a [FormatSpec].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// How a value is formatted by `format`, written as `[[fill]align][0][width][.precision]` after
+/// a colon in the placeholder, like `{:>8.2}`.
+///
+/// - align is `<`, `>` or `^`, and defaults to `>` for numbers and `<` for everything else
+/// - fill is the character to pad with, a space by default
+/// - `0` pads numbers with zeros after their sign
+/// - precision is the amount of digits after the point for floats, or the maximum length of
+///   strings
+///
+/// ```
+/// # use spl::runtime::*;
+/// let spec = FormatSpec::parse("*^7.1").unwrap();
+/// assert_eq!(spec.fill, '*');
+/// assert_eq!(spec.align, Some(Align::Center));
+/// assert_eq!((spec.width, spec.precision), (7, Some(1)));
+/// assert!(FormatSpec::parse("len").is_none());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatSpec {
+    pub fill: char,
+    pub align: Option<Align>,
+    pub zero: bool,
+    pub width: usize,
+    pub precision: Option<usize>,
+}
+
+impl FormatSpec {
+    pub fn parse(s: &str) -> Option<FormatSpec> {
+        let align = |c| match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        };
+        let mut spec = FormatSpec {
+            fill: ' ',
+            align: None,
+            zero: false,
+            width: 0,
+            precision: None,
+        };
+        let mut chars: Vec<char> = s.chars().collect();
+        if let Some(a) = chars.get(1).and_then(|x| align(*x)) {
+            spec.fill = chars[0];
+            spec.align = Some(a);
+            chars.drain(..2);
+        } else if let Some(a) = chars.first().and_then(|x| align(*x)) {
+            spec.align = Some(a);
+            chars.remove(0);
+        }
+        let rest: String = chars.into_iter().collect();
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision.parse().ok()?)),
+            None => (rest.as_str(), None),
+        };
+        spec.precision = precision;
+        let width = match width.strip_prefix('0') {
+            Some(width) => {
+                spec.zero = true;
+                width
+            }
+            None => width,
+        };
+        if !width.is_empty() {
+            spec.width = width.parse().ok()?;
+        }
+        Some(spec)
+    }
+
+    pub fn format(&self, object: &Object) -> String {
+        let (text, is_number) = match &object.native {
+            Value::Null => ("null".to_owned(), false),
+            Value::Bool(x) => (x.to_string(), false),
+            Value::Int(x) => (x.to_string(), true),
+            Value::Long(x) => (x.to_string(), true),
+            Value::Mega(x) => (x.to_string(), true),
+            Value::BigInt(x) => (x.to_string(), true),
+            Value::Decimal(x) => match self.precision {
+                Some(p) => (x.round(p as u32).to_string(), true),
+                None => (x.to_string(), true),
+            },
+            Value::Float(x) => match self.precision {
+                Some(p) => (format!("{x:.p$}"), true),
+                None => (x.to_string(), true),
+            },
+            Value::Double(x) => match self.precision {
+                Some(p) => (format!("{x:.p$}"), true),
+                None => (x.to_string(), true),
+            },
+            Value::Str(x) => match self.precision {
+                Some(p) => (x.chars().take(p).collect(), false),
+                None => (x.clone(), false),
+            },
+            _ => (object.to_string(), false),
+        };
+        let len = text.chars().count();
+        if len >= self.width {
+            return text;
+        }
+        let pad = self.width - len;
+        if self.zero && is_number {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            return format!("{sign}{}{digits}", "0".repeat(pad));
+        }
+        let fill = |n| self.fill.to_string().repeat(n);
+        let default = if is_number { Align::Right } else { Align::Left };
+        match self.align.unwrap_or(default) {
+            Align::Left => text + &fill(pad),
+            Align::Right => fill(pad) + &text,
+            Align::Center => fill(pad / 2) + &text + &fill(pad - pad / 2),
+        }
+    }
+}
+
+/// A part of a format string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FormatPiece {
+    Text(String),
+    Arg(FormatSpec),
+}
+
+/// Splits a format string into text and placeholders, which are written as `{}` or `{:<spec>}`.
+/// `{{` and `}}` are literal braces. Returns None if a placeholder is invalid.
+pub fn parse_format(s: &str) -> Option<Vec<FormatPiece>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next()? {
+                        '}' => break,
+                        c => spec.push(c),
+                    }
+                }
+                let spec = match spec.strip_prefix(':') {
+                    Some(spec) => FormatSpec::parse(spec)?,
+                    None if spec.is_empty() => FormatSpec::parse("")?,
+                    None => return None,
+                };
+                pieces.push(FormatPiece::Text(mem::take(&mut text)));
+                pieces.push(FormatPiece::Arg(spec));
+            }
+            c => text.push(c),
+        }
+    }
+    pieces.push(FormatPiece::Text(text));
+    Some(pieces)
+}
+
 /// Any SPL type.
 ///
 /// This holds:
@@ -838,9 +1808,20 @@ impl Debug for Func {
 pub struct Type {
     name: String,
     id: u32,
+    /// The types included in this one. Added to by [Runtime::include], which keeps the method
+    /// resolution order up to date.
     pub parents: Vec<AMType>,
     pub functions: HashMap<String, AFunc>,
     pub properties: Vec<String>,
+    /// The fields with a default value, and the functions evaluating it.
+    pub defaults: Vec<(String, AFunc)>,
+    /// The ancestors, in the order their methods are looked up in. Updated by `include`.
+    mro: Vec<AMType>,
+    /// Whether this type was declared with `interface`.
+    pub is_interface: bool,
+    /// The methods types including this interface must define, with the signature they must
+    /// have, if any.
+    pub requires: Vec<(String, Option<Signature>)>,
 }
 
 impl PartialEq for Type {
@@ -858,26 +1839,53 @@ impl Type {
         self.id
     }
 
+    /// The ancestors of this type, in the order methods are looked up in: the C3 linearisation
+    /// of the types included in it. This type itself is not part of it.
+    pub fn get_mro(&self) -> Vec<AMType> {
+        self.mro.clone()
+    }
+
+    /// Describes the first method required by `interface` that this type doesn't define, or
+    /// defines with a different signature. Types that aren't interfaces require nothing.
+    pub fn missing(&self, interface: &Type) -> Option<String> {
+        interface.requires.iter().find_map(|(name, required)| {
+            let Some(f) = self.get_fn(name.clone()) else {
+                return Some(format!(
+                    "{} does not define {name}, which {} requires",
+                    self.name, interface.name
+                ));
+            };
+            let (Some(required), Some(signature)) = (required, &f.signature) else {
+                return None;
+            };
+            let compatible = signature.ret_count() == required.ret_count()
+                && required
+                    .returns
+                    .iter()
+                    .zip(&signature.returns)
+                    .all(|(a, b)| *a == TypeSpec::Any || a == b);
+            (!compatible).then(|| {
+                format!(
+                    "{}:{name} returns {{ {signature} }}, but {} requires {{ {required} }}",
+                    self.name, interface.name
+                )
+            })
+        })
+    }
+
     pub fn get_fn(&self, name: String) -> Option<AFunc> {
         if let Some(x) = self.functions.get(&name) {
             return Some(x.clone());
         }
-        let mut q = VecDeque::from(self.parents.clone());
-        while let Some(t) = q.pop_front() {
-            if let Some(x) = t.lock_ro().functions.get(&name) {
-                return Some(x.clone());
-            }
-            q.append(&mut VecDeque::from(t.lock_ro().parents.clone()));
-        }
-        None
+        self.mro
+            .iter()
+            .find_map(|t| t.lock_ro().functions.get(&name).cloned())
     }
 
     pub fn write_into(&self, object: &mut Object) {
         let mut to_apply = self.properties.clone();
-        let mut q = VecDeque::from(self.parents.clone());
-        while let Some(t) = q.pop_front() {
+        for t in &self.mro {
             to_apply.append(&mut t.lock_ro().properties.clone());
-            q.append(&mut VecDeque::from(t.lock_ro().parents.clone()));
         }
         for property in to_apply.into_iter().rev() {
             object
@@ -887,7 +1895,23 @@ impl Type {
         }
     }
 
-    pub fn add_property(&mut self, name: String, origin: Arc<Frame>) -> OError {
+    /// The fields of this type and its parents that have a default value, with the functions
+    /// evaluating it. Defaults of this type come first and replace those of the parents.
+    pub fn get_defaults(&self) -> Vec<(String, AFunc)> {
+        let mut defaults = self.defaults.clone();
+        for t in &self.mro {
+            for (name, f) in &t.lock_ro().defaults {
+                if !defaults.iter().any(|x| &x.0 == name) {
+                    defaults.push((name.clone(), f.clone()));
+                }
+            }
+        }
+        defaults
+    }
+
+    /// Adds a field, along with its getter and its setter. The setter errors when it is given a
+    /// value not matching `kind`.
+    pub fn add_property(&mut self, name: String, kind: TypeSpec, origin: Arc<Frame>) -> OError {
         let tmpname = name.clone();
         self.functions.insert(
             name.clone(),
@@ -911,6 +1935,7 @@ impl Type {
                 }))),
                 origin: origin.clone(),
                 run_as_base: false,
+                signature: None,
                 fname: Some("RUNTIME".to_owned()),
                 name: name.clone(),
             }),
@@ -923,11 +1948,23 @@ impl Type {
                 to_call: FuncImpl::NativeDyn(Arc::new(Box::new(move |stack| {
                     let o = stack.pop();
                     let v = stack.pop();
-                    o.lock().property_map.insert(tmpname.clone(), v);
+                    if !kind.matches(&v) {
+                        return stack.err(ErrorKind::InvalidType(
+                            v.lock_ro().kind.lock_ro().get_name(),
+                            format!(
+                                "{kind} (field {}:{tmpname})",
+                                o.lock_ro().kind.lock_ro().get_name()
+                            ),
+                        ));
+                    }
+                    let mut o = o.lock();
+                    o.check_mutable(stack)?;
+                    o.property_map.insert(tmpname.clone(), v);
                     Ok(())
                 }))),
                 origin,
                 run_as_base: false,
+                signature: None,
                 fname: Some("RUNTIME".to_owned()),
                 name: "=".to_owned() + &name,
             }),
@@ -937,17 +1974,134 @@ impl Type {
     }
 }
 
+/// Creates the type of a construct with the given fields and methods.
+fn make_construct(
+    name: &str,
+    fields: Vec<Field>,
+    methods: Vec<(String, (Signature, Words))>,
+    origin: Arc<Frame>,
+) -> Result<AMType, Error> {
+    runtime_mut(|mut rt| {
+        rt.make_type(name.to_owned(), |mut t| {
+            for field in fields {
+                t.add_property(field.name.clone(), field.kind, origin.clone())?;
+                if let Some(default) = field.default {
+                    let f = Arc::new(Func {
+                        ret_count: 1,
+                        to_call: FuncImpl::SPL(default),
+                        origin: origin.clone(),
+                        run_as_base: false,
+                        signature: None,
+                        fname: None,
+                        name: name.to_owned() + ":" + &field.name,
+                    });
+                    t.defaults.push((field.name, f));
+                }
+            }
+            t.functions.extend(methods.into_iter().map(|(k, v)| {
+                (
+                    k.clone(),
+                    Arc::new(Func {
+                        ret_count: v.0.ret_count(),
+                        to_call: FuncImpl::SPL(v.1),
+                        origin: origin.clone(),
+                        run_as_base: false,
+                        signature: Some(v.0),
+                        fname: None,
+                        name: name.to_owned() + ":" + &k,
+                    }),
+                )
+            }));
+            Ok(t)
+        })
+    })
+}
+
+/// Creates the type of an enum variant, whose constructor takes its fields in order.
+fn make_variant(name: &str, fields: Vec<Field>, origin: Arc<Frame>) -> Result<AMType, Error> {
+    let names: Vec<String> = fields.iter().map(|x| x.name.clone()).collect();
+    let t = make_construct(name, fields, Vec::new(), origin.clone())?;
+    let kind = t.clone();
+    t.lock().functions.insert(
+        "construct".to_owned(),
+        Arc::new(Func {
+            ret_count: 1,
+            to_call: FuncImpl::NativeDyn(Arc::new(Box::new(move |stack| {
+                let this = stack.pop();
+                let mut values: Vec<_> = names.iter().map(|_| stack.pop()).collect();
+                values.reverse();
+                for (field, value) in names.iter().zip(values) {
+                    let setter = kind
+                        .lock_ro()
+                        .get_fn("=".to_owned() + field)
+                        .expect("variant without setter");
+                    stack.push(value);
+                    stack.push(this.clone());
+                    stack.call(&setter)?;
+                }
+                stack.push(this);
+                Ok(())
+            }))),
+            origin,
+            run_as_base: false,
+            signature: None,
+            fname: Some("RUNTIME".to_owned()),
+            name: name.to_owned() + ":construct",
+        }),
+    );
+    Ok(t)
+}
+
+/// The C3 linearisation of a type with the given parents, excluding the type itself. Every type
+/// comes before its parents, and the parents of every type keep their order. None if both can't
+/// be satisfied.
+fn linearize(parents: &[AMType]) -> Option<Vec<AMType>> {
+    let mut sequences: Vec<VecDeque<AMType>> = parents
+        .iter()
+        .map(|p| {
+            let mut sequence = VecDeque::from([p.clone()]);
+            sequence.extend(p.lock_ro().get_mro());
+            sequence
+        })
+        .collect();
+    sequences.push(parents.iter().cloned().collect());
+    let mut output = Vec::new();
+    loop {
+        sequences.retain(|x| !x.is_empty());
+        if sequences.is_empty() {
+            return Some(output);
+        }
+        let head = sequences
+            .iter()
+            .map(|x| &x[0])
+            .find(|head| {
+                !sequences
+                    .iter()
+                    .any(|x| x.iter().skip(1).any(|t| t == *head))
+            })?
+            .clone();
+        for sequence in &mut sequences {
+            if sequence[0] == head {
+                sequence.pop_front();
+            }
+        }
+        output.push(head);
+    }
+}
+
 /// Any kind of SPL object, no matter if it is a construct or not.
 ///
 /// This holds:
 /// - the type of the object
 /// - the fields mandated by the type
 /// - the native value ([Value]), null for constructs unless set manually.
+/// - whether it has been frozen, after which its fields and contents can not be changed.
 #[derive(Clone, Debug)]
 pub struct Object {
     pub kind: AMType,
     pub property_map: HashMap<String, AMObject>,
     pub native: Value,
+    pub frozen: bool,
 }
 
 impl PartialEq for Object {
@@ -988,27 +2142,125 @@ impl Display for Object {
 }
 
 impl Object {
+    /// Writes a readable representation of the object: constructs as `Type { field: value, ... }`
+    /// with one field per line, and native values the way they are written in SPL. `hook` can
+    /// provide the representation of nested objects instead. Objects that contain themselves are
+    /// written as `<cycle Type>`.
+    pub fn write_debug<E>(
+        &self,
+        output: &mut String,
+        indent: usize,
+        path: &mut Vec<*const Object>,
+        hook: &mut impl FnMut(&AMObject) -> Result<Option<String>, E>,
+    ) -> Result<(), E> {
+        let kind = self.kind.lock_ro().name.clone();
+        let this = self as *const Object;
+        if path.contains(&this) {
+            *output += &format!("<cycle {kind}>");
+            return Ok(());
+        }
+        path.push(this);
+        let mut child = |output: &mut String, object: &AMObject, indent| {
+            match hook(object)? {
+                Some(s) => *output += &s.replace('\n', &("\n".to_owned() + &"    ".repeat(indent))),
+                None => object.lock_ro().write_debug(output, indent, path, hook)?,
+            }
+            Ok(())
+        };
+        match &self.native {
+            Value::Null if self.is_construct() => {
+                let mut fields: Vec<_> = self.property_map.iter().collect();
+                fields.sort_by(|a, b| a.0.cmp(b.0));
+                if fields.is_empty() {
+                    *output += &format!("{kind} {{}}");
+                } else {
+                    *output += &format!("{kind} {{\n");
+                    for (name, object) in fields {
+                        *output += &"    ".repeat(indent + 1);
+                        *output += name;
+                        *output += ": ";
+                        child(output, object, indent + 1)?;
+                        *output += ",\n";
+                    }
+                    *output += &"    ".repeat(indent);
+                    *output += "}";
+                }
+            }
+            Value::Null => *output += "null",
+            Value::Int(x) => *output += &format!("{x}i"),
+            Value::Long(x) => *output += &format!("{x}l"),
+            Value::Mega(x) => *output += &x.to_string(),
+            Value::BigInt(x) => *output += &format!("{x}n"),
+            Value::Decimal(x) => *output += &format!("{x}m"),
+            Value::Float(x) => *output += &format!("{x:?}f"),
+            Value::Double(x) => *output += &format!("{x:?}"),
+            Value::Func(x) => *output += &format!("func {}", x.name),
+            Value::Array(x) => {
+                *output += "[";
+                for (i, object) in x.iter().enumerate() {
+                    if i != 0 {
+                        *output += ", ";
+                    }
+                    child(output, object, indent)?;
+                }
+                *output += "]";
+            }
+            Value::Str(x) => *output += &format!("{x:?}"),
+            Value::Bool(x) => *output += &x.to_string(),
+            Value::Bytes(x) => {
+                *output += "bytes \"";
+                for b in x {
+                    *output += &format!("{b:02x}");
+                }
+                *output += "\"";
+            }
+        }
+        path.pop();
+        Ok(())
+    }
+
+    /// Whether this is an instance of a construct, as opposed to a native value.
+    pub fn is_construct(&self) -> bool {
+        matches!(self.native, Value::Null) && self.kind.lock_ro().id != 0
+    }
+
     pub fn new(kind: AMType, native: Value) -> Object {
         let mut r = Object {
             property_map: HashMap::new(),
             kind: kind.clone(),
             native,
+            frozen: false,
         };
         kind.lock_ro().write_into(&mut r);
         r
     }
 
+    /// Fails with ReadOnly if the object has been frozen.
+    pub fn check_mutable(&self, stack: &Stack) -> OError {
+        if self.frozen {
+            return stack.err(ErrorKind::ReadOnly(self.kind.lock_ro().get_name()));
+        }
+        Ok(())
+    }
+
+    /// Whether this counts as true for if, while, not, and and or: bools are what they are,
+    /// null is false and constructs are true, integers and decimals are true if they are greater
+    /// than zero, strings and bytes if they aren't empty, and floats, functions and arrays always.
     pub fn is_truthy(&self) -> bool {
         match &self.native {
             Value::Null => self.kind.lock_ro().id != 0,
+            Value::Bool(x) => *x,
             Value::Int(x) => x > &0,
             Value::Long(x) => x > &0,
             Value::Mega(x) => x > &0,
+            Value::BigInt(x) => !x.is_zero() && !x.is_negative(),
+            Value::Decimal(x) => !x.is_zero() && !x.is_negative(),
             Value::Float(_) => true,
             Value::Double(_) => true,
             Value::Func(_) => true,
             Value::Array(_) => true,
             Value::Str(x) => !x.is_empty(),
+            Value::Bytes(x) => !x.is_empty(),
         }
     }
 
@@ -1068,6 +2320,10 @@ impl From<Value> for Object {
                     Value::Func(_) => x.get_type_by_id(6),
                     Value::Array(_) => x.get_type_by_id(7),
                     Value::Str(_) => x.get_type_by_id(8),
+                    Value::Bool(_) => x.get_type_by_id(9),
+                    Value::Bytes(_) => x.get_type_by_id(10),
+                    Value::BigInt(_) => x.get_type_by_id(11),
+                    Value::Decimal(_) => x.get_type_by_id(12),
                 }
                 .expect("runtime uninitialized: default types not set.")
             }),
@@ -1133,15 +2389,22 @@ impl Words {
         for word in self.words.clone() {
             match word {
                 Word::Key(x) => match x {
-                    Keyword::Dump => println!("{stack}"),
+                    Keyword::Dump => println!("{}", stack.dump()?),
                     Keyword::Def(x) => stack.define_var(x),
+                    Keyword::Const(name, value) => {
+                        stack.define_var(name.clone());
+                        value.exec(stack)?;
+                        let value = stack.pop();
+                        stack.define_const(name, value)?;
+                    }
                     Keyword::Func(name, rem, words) => stack.define_func(
                         name.clone(),
                         Arc::new(Func {
-                            ret_count: rem,
+                            ret_count: rem.ret_count(),
                             to_call: FuncImpl::SPL(words),
                             origin: stack.get_frame(),
                             run_as_base: false,
+                            signature: Some(rem),
                             fname: None,
                             name,
                         }),
@@ -1151,35 +2414,14 @@ impl Words {
                         if !name.contains(':') {
                             stack.define_var(name.clone());
                         }
-                        let t = runtime_mut(|mut rt| {
-                            rt.make_type(name.clone(), |mut t| {
-                                for field in fields {
-                                    t.add_property(field, origin.clone())?;
-                                }
-                                t.functions.extend(methods.into_iter().map(|(k, v)| {
-                                    (
-                                        k.clone(),
-                                        Arc::new(Func {
-                                            ret_count: v.0,
-                                            to_call: FuncImpl::SPL(v.1),
-                                            origin: origin.clone(),
-                                            run_as_base: false,
-                                            fname: None,
-                                            name: name.clone() + ":" + &k,
-                                        }),
-                                    )
-                                }));
-                                Ok(t)
-                            })
-                        })?;
+                        let t = make_construct(&name, fields, methods, origin)?;
 
-                        let to_set: Object = if is_namespace {
-                            let mut obj: Object = Value::Null.into();
-                            obj.kind = t.clone();
-                            t.lock_ro().write_into(&mut obj);
+                        let to_set = if is_namespace {
+                            let obj = Value::Null.spl();
+                            stack.set_type(&obj, t.clone())?;
                             obj
                         } else {
-                            Value::Str(t.lock_ro().get_name()).into()
+                            Value::Str(t.lock_ro().get_name()).spl()
                         };
                         if name.contains(':') {
                             let Some((a, mut name)) = name.split_once(':') else { unreachable!() };
@@ -1191,23 +2433,104 @@ impl Words {
                                 mem::drop(o);
                                 f = nf;
                             }
-                            *f.lock_ro().field(name, stack)?.lock() = to_set;
+                            *f.lock_ro().field(name, stack)?.lock() = to_set.lock_ro().clone();
                         } else {
-                            stack.set_var(name.clone(), to_set.spl())?;
+                            stack.set_var(name.clone(), to_set)?;
                         }
                     }
+                    Keyword::Interface(name, requires, methods) => {
+                        Words::new(vec![Word::Key(Keyword::Construct(
+                            name.clone(),
+                            Vec::new(),
+                            methods,
+                            false,
+                        ))])
+                        .exec(stack)?;
+                        let t = runtime(|rt| rt.get_type_by_name(&name))
+                            .ok_or_else(|| stack.error(ErrorKind::TypeNotFound(name)))?;
+                        let mut t = t.lock();
+                        t.is_interface = true;
+                        t.requires = requires;
+                    }
+                    Keyword::Enum(name, variants, methods) => {
+                        Words::new(vec![Word::Key(Keyword::Construct(
+                            name.clone(),
+                            Vec::new(),
+                            methods,
+                            true,
+                        ))])
+                        .exec(stack)?;
+                        let origin = stack.get_frame();
+                        let parent = runtime(|rt| rt.get_type_by_name(&name))
+                            .ok_or_else(|| stack.error(ErrorKind::TypeNotFound(name.clone())))?;
+                        let mut variant_names = Vec::new();
+                        for (variant, fields) in variants {
+                            let full = name.clone() + ":" + &variant;
+                            let t = make_variant(&full, fields, origin.clone())?;
+                            runtime_mut(|mut rt| rt.include(parent.clone(), t))
+                                .map_err(|x| stack.error(ErrorKind::Custom(x)))?;
+                            parent.lock().functions.insert(
+                                variant.clone(),
+                                Arc::new(Func {
+                                    ret_count: 1,
+                                    to_call: FuncImpl::NativeDyn(Arc::new(Box::new(
+                                        move |stack| {
+                                            stack.pop();
+                                            stack.push(Value::Str(full.clone()).spl());
+                                            Ok(())
+                                        },
+                                    ))),
+                                    origin: origin.clone(),
+                                    run_as_base: false,
+                                    signature: None,
+                                    fname: Some("RUNTIME".to_owned()),
+                                    name: name.clone() + ":" + &variant,
+                                }),
+                            );
+                            variant_names.push(variant);
+                        }
+                        let prefix = name.clone() + ":";
+                        parent.lock().functions.insert(
+                            "is-variant?".to_owned(),
+                            Arc::new(Func {
+                                ret_count: 1,
+                                to_call: FuncImpl::NativeDyn(Arc::new(Box::new(move |stack| {
+                                    let this = stack.pop();
+                                    let variant = stack.pop();
+                                    let Value::Str(variant) = variant.lock_ro().native.clone()
+                                    else {
+                                        return stack
+                                            .err(ErrorKind::InvalidCall("is-variant?".to_owned()));
+                                    };
+                                    let variant = variant.strip_prefix(&prefix).unwrap_or(&variant);
+                                    if !variant_names.iter().any(|x| x == variant) {
+                                        return stack.err(ErrorKind::TypeNotFound(
+                                            prefix.clone() + variant,
+                                        ));
+                                    }
+                                    let kind = this.lock_ro().kind.lock_ro().get_name();
+                                    stack.push(Value::Bool(kind == prefix.clone() + variant).spl());
+                                    Ok(())
+                                }))),
+                                origin,
+                                run_as_base: false,
+                                signature: None,
+                                fname: Some("RUNTIME".to_owned()),
+                                name: name + ":is-variant?",
+                            }),
+                        );
+                    }
                     Keyword::Include(ta, tb) => {
                         let rstack = &stack;
-                        runtime(move |rt| {
-                            rt.get_type_by_name(&tb)
-                                .ok_or_else(|| rstack.error(ErrorKind::TypeNotFound(tb)))?
-                                .lock()
-                                .parents
-                                .push(
-                                    rt.get_type_by_name(&ta)
-                                        .ok_or_else(|| rstack.error(ErrorKind::TypeNotFound(ta)))?,
-                                );
-                            Ok(())
+                        runtime_mut(move |mut rt| {
+                            let child = rt
+                                .get_type_by_name(&tb)
+                                .ok_or_else(|| rstack.error(ErrorKind::TypeNotFound(tb)))?;
+                            let parent = rt
+                                .get_type_by_name(&ta)
+                                .ok_or_else(|| rstack.error(ErrorKind::TypeNotFound(ta)))?;
+                            rt.include(parent, child)
+                                .map_err(|x| rstack.error(ErrorKind::Custom(x)))
                         })?;
                     }
                     Keyword::Use(item) => {
@@ -1227,6 +2550,13 @@ impl Words {
                     }
                     Keyword::While(cond, blk) => loop {
                         cond.exec(stack)?;
+                        // a jump out of the condition leaves no condition to check
+                        if stack.jump.is_some() {
+                            if stack.leave_loop() {
+                                break;
+                            }
+                            continue;
+                        }
                         if !stack.pop().lock_ro().is_truthy() {
                             break;
                         }
@@ -1235,27 +2565,127 @@ impl Words {
                             stack.return_accumultor -= 1;
                             break;
                         }
+                        if stack.leave_loop() {
+                            break;
+                        }
                     },
+                    Keyword::For(var, iterable, blk) => {
+                        iterable.exec(stack)?;
+                        let mut iter = stack.pop();
+                        if iter
+                            .lock_ro()
+                            .kind
+                            .lock_ro()
+                            .get_fn("next".to_owned())
+                            .is_none()
+                        {
+                            let f = stack.get_method(&iter, "iter")?;
+                            stack.push(iter);
+                            stack.call(&f)?;
+                            iter = stack.pop();
+                        }
+                        let next = stack.get_method(&iter, "next")?;
+                        stack.define_var(var.clone());
+                        loop {
+                            stack.push(iter.clone());
+                            stack.call(&next)?;
+                            let item = stack.pop();
+                            if *item.lock_ro() == Value::Null.into() {
+                                break;
+                            }
+                            stack.set_var(var.clone(), item)?;
+                            blk.exec(stack)?;
+                            if stack.return_accumultor > 0 {
+                                stack.return_accumultor -= 1;
+                                break;
+                            }
+                            if stack.leave_loop() {
+                                break;
+                            }
+                        }
+                    }
+                    Keyword::Break => stack.jump = Some(Jump::Break),
+                    Keyword::Continue => stack.jump = Some(Jump::Continue),
+                    Keyword::Return => stack.jump = Some(Jump::Return),
+                    Keyword::Super(name, rem) => {
+                        let kind = stack.peek().lock_ro().kind.clone();
+                        let owner = stack.method_type().ok_or_else(|| {
+                            stack.error(ErrorKind::InvalidCall("super".to_owned()))
+                        })?;
+                        let mro = kind.lock_ro().get_mro();
+                        let f = [kind]
+                            .into_iter()
+                            .chain(mro)
+                            .skip_while(|t| *t != owner)
+                            .skip(1)
+                            .find_map(|t| t.lock_ro().functions.get(&name).cloned())
+                            .ok_or_else(|| {
+                                stack.error(ErrorKind::MethodNotFound(
+                                    owner.lock_ro().get_name(),
+                                    "super:".to_owned() + &name,
+                                ))
+                            })?;
+                        stack.call(&f)?;
+                        if rem {
+                            for _ in 0..f.ret_count {
+                                stack.pop();
+                            }
+                        }
+                    }
                     Keyword::If(blk) => {
                         if stack.pop().lock_ro().is_truthy() {
                             blk.exec(stack)?;
                         }
                     }
-                    Keyword::Catch(types, blk, ctch) => {
-                        if let Err(e) = blk.exec(stack) {
-                            if types.is_empty() || types.contains(&e.kind.to_string()) {
-                                stack.push(e.spl());
-                                ctch.exec(stack)?;
-                            } else {
-                                return Err(e);
+                    Keyword::IfElse(blk, els) => {
+                        if stack.pop().lock_ro().is_truthy() {
+                            blk.exec(stack)?;
+                        } else {
+                            els.exec(stack)?;
+                        }
+                    }
+                    Keyword::Match(arms, default) => {
+                        let value = stack.pop();
+                        let mut arms = arms.into_iter();
+                        loop {
+                            let Some((pattern, blk)) = arms.next() else {
+                                if let Some(default) = default {
+                                    default.exec(stack)?;
+                                }
+                                break;
+                            };
+                            if pattern.matches(&value, stack)? {
+                                blk.exec(stack)?;
+                                break;
                             }
                         }
                     }
-                    Keyword::With(vars) => {
-                        for var in vars.into_iter().rev() {
-                            stack.define_var(var.clone());
+                    Keyword::Catch(types, blk, ctch, finally) => {
+                        let result = match blk.exec(stack) {
+                            Err(e) if types.is_empty() || types.iter().any(|x| e.kind.is(x)) => {
+                                e.into_object(stack).and_then(|e| {
+                                    stack.push(e);
+                                    ctch.exec(stack)
+                                })
+                            }
+                            result => result,
+                        };
+                        if let Some(finally) = finally {
+                            // a return, break or continue only takes effect after finally.
+                            let jump = stack.jump.take();
+                            let returns = mem::take(&mut stack.return_accumultor);
+                            finally.exec(stack)?;
+                            if stack.jump.is_none() && stack.return_accumultor == 0 {
+                                stack.jump = jump;
+                                stack.return_accumultor = returns;
+                            }
+                        }
+                        result?;
+                    }
+                    Keyword::With(bindings) => {
+                        for binding in bindings.into_iter().rev() {
                             let obj = stack.pop();
-                            stack.set_var(var, obj)?;
+                            binding.bind(obj, stack)?;
                         }
                     }
                     Keyword::ObjPush => {
@@ -1278,6 +2708,7 @@ impl Words {
                                 to_call: f.1.clone(),
                                 origin: stack.get_frame(),
                                 run_as_base: false,
+                                signature: None,
                                 fname: None,
                                 name,
                             }),
@@ -1311,6 +2742,7 @@ impl Words {
                                 }))),
                                 origin: stack.get_frame(),
                                 run_as_base: false,
+                                signature: None,
                                 fname: None,
                                 name: s + &x,
                             }));
@@ -1356,6 +2788,7 @@ impl Words {
                                 }))),
                                 origin: stack.get_frame(),
                                 run_as_base: false,
+                                signature: None,
                                 fname: None,
                                 name: s + &x,
                             }));
@@ -1376,6 +2809,9 @@ impl Words {
                 stack.return_accumultor -= 1;
                 return Ok(());
             }
+            if stack.jump.is_some() {
+                return Ok(());
+            }
         }
         Ok(())
     }
@@ -1394,10 +2830,83 @@ pub enum ErrorKind {
     TypeNotFound(String),
     LexError(String),
     IO(String),
+    /// An arithmetic operation or conversion whose result doesn't fit in its type.
+    Overflow(String),
+    DivisionByZero(String),
+    /// A value taken by `with` that doesn't fit its pattern: the pattern and what the value was.
+    PatternMismatch(String, String),
+    /// An attempt to reassign a constant or change a frozen object: the variable, or the type of
+    /// the object.
+    ReadOnly(String),
     Custom(String),
+    /// An object passed to throw. If its type includes `error`, the object itself is what catch
+    /// passes on, with the fields it didn't set filled in.
     CustomObject(AMObject),
 }
 
+impl ErrorKind {
+    /// Checks if this is of the given kind: either its name, `error`, or the name of the type of
+    /// the thrown object or of one it includes.
+    pub fn is(&self, kind: &str) -> bool {
+        // every error reaches its handler as an object including error
+        if self.to_string() == kind || kind == "error" {
+            return true;
+        }
+        let ErrorKind::CustomObject(o) = self else {
+            return false;
+        };
+        let t = o.lock_ro().kind.clone();
+        let t = t.lock_ro();
+        t.get_name() == kind || t.get_mro().iter().any(|x| x.lock_ro().get_name() == kind)
+    }
+
+    /// The values of this error, as stored in the args field of error objects.
+    fn args(&self) -> Vec<String> {
+        match self {
+            ErrorKind::Parse(a, b)
+            | ErrorKind::InvalidType(a, b)
+            | ErrorKind::MethodNotFound(a, b)
+            | ErrorKind::PropertyNotFound(a, b)
+            | ErrorKind::PatternMismatch(a, b) => vec![a.clone(), b.clone()],
+            ErrorKind::InvalidCall(a)
+            | ErrorKind::VariableNotFound(a)
+            | ErrorKind::FuncNotFound(a)
+            | ErrorKind::TypeNotFound(a)
+            | ErrorKind::LexError(a)
+            | ErrorKind::IO(a)
+            | ErrorKind::Overflow(a)
+            | ErrorKind::DivisionByZero(a)
+            | ErrorKind::ReadOnly(a)
+            | ErrorKind::Custom(a) => vec![a.clone()],
+            ErrorKind::CustomObject(_) => Vec::new(),
+        }
+    }
+
+    /// Creates an error from its name and values, as stored in error objects.
+    fn from_parts(kind: &str, mut args: Vec<String>, object: AMObject) -> Option<ErrorKind> {
+        let mut arg = || (!args.is_empty()).then(|| args.remove(0));
+        Some(match kind {
+            "Parse" => ErrorKind::Parse(arg()?, arg()?),
+            "InvalidCall" => ErrorKind::InvalidCall(arg()?),
+            "InvalidType" => ErrorKind::InvalidType(arg()?, arg()?),
+            "VariableNotFound" => ErrorKind::VariableNotFound(arg()?),
+            "FuncNotFound" => ErrorKind::FuncNotFound(arg()?),
+            "MethodNotFound" => ErrorKind::MethodNotFound(arg()?, arg()?),
+            "PropertyNotFound" => ErrorKind::PropertyNotFound(arg()?, arg()?),
+            "TypeNotFound" => ErrorKind::TypeNotFound(arg()?),
+            "LexError" => ErrorKind::LexError(arg()?),
+            "IO" => ErrorKind::IO(arg()?),
+            "Overflow" => ErrorKind::Overflow(arg()?),
+            "DivisionByZero" => ErrorKind::DivisionByZero(arg()?),
+            "PatternMismatch" => ErrorKind::PatternMismatch(arg()?, arg()?),
+            "ReadOnly" => ErrorKind::ReadOnly(arg()?),
+            "Custom" => ErrorKind::Custom(arg()?),
+            "CustomObject" => ErrorKind::CustomObject(object),
+            _ => return None,
+        })
+    }
+}
+
 impl Display for ErrorKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1411,6 +2920,10 @@ impl Display for ErrorKind {
             ErrorKind::TypeNotFound(_) => f.write_str("TypeNotFound"),
             ErrorKind::LexError(_) => f.write_str("LexError"),
             ErrorKind::IO(_) => f.write_str("IO"),
+            ErrorKind::Overflow(_) => f.write_str("Overflow"),
+            ErrorKind::DivisionByZero(_) => f.write_str("DivisionByZero"),
+            ErrorKind::PatternMismatch(_, _) => f.write_str("PatternMismatch"),
+            ErrorKind::ReadOnly(_) => f.write_str("ReadOnly"),
             ErrorKind::Custom(_) => f.write_str("Custom"),
             ErrorKind::CustomObject(_) => f.write_str("CustomObject"),
         }
@@ -1436,18 +2949,143 @@ impl Debug for Error {
     }
 }
 
+/// Checks if a type is `error` or includes it.
+fn is_error_type(kind: &AMType) -> bool {
+    let kind = kind.lock_ro();
+    kind.get_name() == "error"
+        || kind
+            .get_mro()
+            .iter()
+            .any(|x| x.lock_ro().get_name() == "error")
+}
+
+impl Error {
+    /// Recreates an error from an error object, as created by [Error::into_object] when it is
+    /// caught, keeping its traces. None if the object isn't one.
+    pub fn from_object(obj: &AMObject) -> Option<Error> {
+        let object = obj.lock_ro();
+        let field = |name: &str| {
+            object
+                .property_map
+                .get(name)
+                .map(|x| x.lock_ro().native.clone())
+        };
+        let Some(Value::Str(kind)) = field("kind") else {
+            return None;
+        };
+        let strings = |value: Option<Value>| match value {
+            Some(Value::Array(x)) => x
+                .iter()
+                .map(|x| match &x.lock_ro().native {
+                    Value::Str(x) => Some(x.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        };
+        // objects of types including error are thrown as themselves.
+        let kind = if object.kind.lock_ro().get_name() != "error" && is_error_type(&object.kind) {
+            ErrorKind::CustomObject(obj.clone())
+        } else {
+            ErrorKind::from_parts(
+                &kind,
+                strings(field("args"))?,
+                object.property_map.get("object")?.clone(),
+            )?
+        };
+        let Some(Value::Array(mr_stack)) = field("mr-trace") else {
+            return None;
+        };
+        let mr_stack = mr_stack
+            .iter()
+            .map(|frames| match &frames.lock_ro().native {
+                Value::Array(frames) => frames
+                    .iter()
+                    .map(|frame| {
+                        let frame = frame.lock_ro();
+                        let field =
+                            |name: &str| match &frame.property_map.get(name)?.lock_ro().native {
+                                Value::Str(x) => Some(x.clone()),
+                                _ => None,
+                            };
+                        Some(FrameInfo {
+                            file: field("file")?,
+                            function: field("function")?,
+                        })
+                    })
+                    .collect(),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        Some(Error {
+            kind,
+            stack: strings(field("trace"))?,
+            mr_stack,
+        })
+    }
+
+    /// The object describing this error in SPL, as passed to catch handlers. This is a new
+    /// `error`, unless the error is an object of a type including `error`. Then it is that
+    /// object, with every field it didn't set yet filled in through its setters, unless it is
+    /// frozen. Fails if a setter does, for example because the field has a different type.
+    pub fn into_object(self, stack: &mut Stack) -> Result<AMObject, Error> {
+        let ErrorKind::CustomObject(ref o) = self.kind else {
+            return Ok(Object::from(self).spl());
+        };
+        if !is_error_type(&o.lock_ro().kind) {
+            return Ok(Object::from(self).spl());
+        }
+        let o = o.clone();
+        if o.lock_ro().frozen {
+            return Ok(o);
+        }
+        let name = o.lock_ro().kind.lock_ro().get_name();
+        let fields = [
+            ("kind", self.kind.to_string().spl()),
+            ("message", name.spl()),
+            ("args", Vec::<String>::new().spl()),
+            ("trace", self.stack.spl()),
+            ("mr-trace", self.mr_stack.spl()),
+        ];
+        for (name, value) in fields {
+            let is_null = o.lock_ro().property_map.get(name).is_none_or(|field| {
+                let field = field.lock_ro();
+                matches!(field.native, Value::Null) && !field.is_construct()
+            });
+            if is_null {
+                let setter = stack.get_method(&o, &("=".to_owned() + name))?;
+                stack.push(value);
+                stack.push(o.clone());
+                stack.call(&setter)?;
+            }
+        }
+        Ok(o)
+    }
+}
+
 impl From<Error> for Object {
     fn from(value: Error) -> Self {
+        if let ErrorKind::CustomObject(ref o) = value.kind {
+            if is_error_type(&o.lock_ro().kind) {
+                return o.lock_ro().clone();
+            }
+        }
         let mut obj = Object::new(
             get_type("error").expect("error type must exist"),
             Value::Null,
         );
         obj.property_map
             .insert("kind".to_owned(), value.kind.to_string().spl());
+        obj.property_map
+            .insert("args".to_owned(), value.kind.args().spl());
         obj.property_map
             .insert("message".to_owned(), format!("{:?}", value.kind).spl());
         if let ErrorKind::CustomObject(ref o) = value.kind {
             obj.property_map.insert("object".to_owned(), o.clone());
+            obj.property_map.insert(
+                "message".to_owned(),
+                format!("CustomObject({})", o.lock_ro()).spl(),
+            );
         }
         if let ErrorKind::Custom(ref s) = value.kind {
             obj.property_map
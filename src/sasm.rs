@@ -1,6 +1,68 @@
 use std::{sync::Arc, time::SystemTime};
 
-use crate::{Frame, Func, FuncImpl, FuncImplType, Keyword, Value, Word, Words};
+use crate::{
+    bignum::{BigInt, Decimal},
+    lexer::parse_bindings,
+    runtime::split_top_level,
+    Field, Frame, Func, FuncImpl, FuncImplType, Keyword, Pattern, Signature, TypeSpec, Value, Word,
+    Words,
+};
+
+/// Reads a signature written by [write_signature]. Signatures written as just the return count
+/// are accepted as well, and return values of any type.
+fn read_signature(s: &str) -> Option<Signature> {
+    let mut parts = split_top_level(s, ',').into_iter();
+    let ret_count = parts.next()?.parse().ok()?;
+    let types: Vec<_> = parts.collect();
+    if types.is_empty() {
+        return Some(Signature::any(ret_count));
+    }
+    let signature = Signature::parse(&types);
+    (signature.ret_count() == ret_count).then_some(signature)
+}
+
+/// Writes a signature as its return count, followed by the types, separated by commas.
+fn write_signature(signature: &Signature) -> String {
+    let mut output = signature.ret_count().to_string();
+    for spec in &signature.returns {
+        output += ",";
+        output += &spec.to_string();
+    }
+    output
+}
+
+/// Reads a field as `<name>[:<type>][=]`, where = means a default follows the methods. The
+/// default itself is left empty.
+fn read_field(word: &str) -> Field {
+    let (word, has_default) = match word.strip_suffix('=') {
+        Some(word) => (word, true),
+        None => (word, false),
+    };
+    let mut field = match word.split_once(':') {
+        Some((name, kind)) => {
+            let mut field = Field::new(name.to_owned());
+            field.kind = TypeSpec::parse(kind);
+            field
+        }
+        None => Field::new(word.to_owned()),
+    };
+    if has_default {
+        field.default = Some(Words::new(Vec::new()));
+    }
+    field
+}
+
+/// Writes a field as read by [read_field].
+fn write_field(field: &Field) -> String {
+    let mut output = field.name.clone();
+    if field.kind != TypeSpec::Any {
+        output += &format!(":{}", field.kind);
+    }
+    if field.default.is_some() {
+        output += "=";
+    }
+    output
+}
 
 /// Reads sasm, the text representation of an SPL AST.
 pub fn sasm_read(s: String) -> Words {
@@ -25,9 +87,13 @@ fn sasm_parse<'a>(line: &str, words: &mut Vec<Word>, lines: &mut impl Iterator<I
     match line[0] {
         "dump" => words.push(Word::Key(Keyword::Dump)),
         "def" => words.push(Word::Key(Keyword::Def(line[1].to_owned()))),
+        "constant" => words.push(Word::Key(Keyword::Const(
+            line[1].to_owned(),
+            sasm_read_func(lines),
+        ))),
         "func" => words.push(Word::Key(Keyword::Func(
             line[1].to_owned(),
-            line[2].parse().expect("invalid sasm func: func ... NAN"),
+            read_signature(line[2]).expect("invalid sasm func: func ... NAN"),
             sasm_read_func(lines),
         ))),
         "construct" => {
@@ -39,7 +105,7 @@ fn sasm_parse<'a>(line: &str, words: &mut Vec<Word>, lines: &mut impl Iterator<I
                 if word == ";" {
                     break;
                 }
-                fields.push(word.to_owned());
+                fields.push(read_field(word));
             }
             while let Some(word) = iter.next() {
                 if word == ";" {
@@ -49,13 +115,17 @@ fn sasm_parse<'a>(line: &str, words: &mut Vec<Word>, lines: &mut impl Iterator<I
                     word.to_owned(),
                     (
                         iter.next()
-                            .map(|x| x.parse().ok())
-                            .flatten()
+                            .and_then(read_signature)
                             .expect("invalid sasm construct: construct .... ; ... NAN ...."),
                         sasm_read_func(lines),
                     ),
                 ));
             }
+            for field in &mut fields {
+                if field.default.is_some() {
+                    field.default = Some(sasm_read_func(lines));
+                }
+            }
             words.push(Word::Key(Keyword::Construct(
                 name,
                 fields,
@@ -66,6 +136,81 @@ fn sasm_parse<'a>(line: &str, words: &mut Vec<Word>, lines: &mut impl Iterator<I
                 },
             )))
         }
+        "enum" => {
+            let name = line[1].to_owned();
+            let mut variants: Vec<(String, Vec<Field>)> = Vec::new();
+            let mut methods = Vec::new();
+            let mut iter = line.into_iter().skip(2);
+            // <variant> [( <field> <...> )] <...>
+            while let Some(word) = iter.next() {
+                match word {
+                    ";" => break,
+                    "(" => {
+                        let variant = variants
+                            .last_mut()
+                            .expect("invalid sasm enum: enum .... ( without variant");
+                        for word in &mut iter {
+                            if word == ")" {
+                                break;
+                            }
+                            variant.1.push(read_field(word));
+                        }
+                    }
+                    _ => variants.push((word.to_owned(), Vec::new())),
+                }
+            }
+            while let Some(word) = iter.next() {
+                methods.push((
+                    word.to_owned(),
+                    (
+                        iter.next()
+                            .and_then(read_signature)
+                            .expect("invalid sasm enum: enum .... ; ... NAN ...."),
+                        sasm_read_func(lines),
+                    ),
+                ));
+            }
+            for field in variants.iter_mut().flat_map(|x| &mut x.1) {
+                if field.default.is_some() {
+                    field.default = Some(sasm_read_func(lines));
+                }
+            }
+            words.push(Word::Key(Keyword::Enum(name, variants, methods)))
+        }
+        "interface" => {
+            let name = line[1].to_owned();
+            let mut requires = Vec::new();
+            let mut methods = Vec::new();
+            let mut iter = line.into_iter().skip(2);
+            for word in &mut iter {
+                if word == ";" {
+                    break;
+                }
+                // <name>[:<signature>]
+                requires.push(match word.split_once(':') {
+                    Some((name, signature)) => (
+                        name.to_owned(),
+                        Some(
+                            read_signature(signature)
+                                .expect("invalid sasm interface: interface .... NAME:NAN"),
+                        ),
+                    ),
+                    None => (word.to_owned(), None),
+                });
+            }
+            while let Some(word) = iter.next() {
+                methods.push((
+                    word.to_owned(),
+                    (
+                        iter.next()
+                            .and_then(read_signature)
+                            .expect("invalid sasm interface: interface .... ; ... NAN ...."),
+                        sasm_read_func(lines),
+                    ),
+                ));
+            }
+            words.push(Word::Key(Keyword::Interface(name, requires, methods)))
+        }
         "include" => words.push(Word::Key(Keyword::Include(
             line[1].to_owned(),
             line[2].to_owned(),
@@ -76,14 +221,77 @@ fn sasm_parse<'a>(line: &str, words: &mut Vec<Word>, lines: &mut impl Iterator<I
             sasm_read_func(lines),
         ))),
         "if" => words.push(Word::Key(Keyword::If(sasm_read_func(lines)))),
-        "with" => words.push(Word::Key(Keyword::With(
-            line.into_iter().skip(1).map(ToOwned::to_owned).collect(),
+        "ifelse" => words.push(Word::Key(Keyword::IfElse(
+            sasm_read_func(lines),
+            sasm_read_func(lines),
         ))),
-        "catch" => words.push(Word::Key(Keyword::Catch(
-            line.into_iter().skip(1).map(ToOwned::to_owned).collect(),
+        "match" => {
+            // match <arms> [default] [<index>(<name>,<...>) <...>], where the indices are those
+            // of destructuring arms.
+            let has_default = line.get(2) == Some(&"default");
+            let destructure: Vec<(usize, Vec<String>)> = line
+                .iter()
+                .skip(if has_default { 3 } else { 2 })
+                .map(|x| {
+                    let (i, names) = x
+                        .strip_suffix(')')
+                        .and_then(|x| x.split_once('('))
+                        .expect("invalid sasm match: match .... NAN(...)");
+                    (
+                        i.parse().expect("invalid sasm match: match .... NAN(...)"),
+                        names
+                            .split(',')
+                            .filter(|x| !x.is_empty())
+                            .map(str::to_owned)
+                            .collect(),
+                    )
+                })
+                .collect();
+            let arms = (0..line[1].parse().expect("invalid sasm match: match NAN"))
+                .map(|i| {
+                    let words = sasm_read_func(lines);
+                    let pattern = match destructure.iter().find(|x| x.0 == i) {
+                        Some((_, names)) => Pattern::Destructure(words, names.clone()),
+                        None => Pattern::Value(words),
+                    };
+                    (pattern, sasm_read_func(lines))
+                })
+                .collect();
+            let default = has_default.then(|| sasm_read_func(lines));
+            words.push(Word::Key(Keyword::Match(arms, default)))
+        }
+        "for" => words.push(Word::Key(Keyword::For(
+            line[1].to_owned(),
             sasm_read_func(lines),
             sasm_read_func(lines),
         ))),
+        "break" => words.push(Word::Key(Keyword::Break)),
+        "continue" => words.push(Word::Key(Keyword::Continue)),
+        "return" => words.push(Word::Key(Keyword::Return)),
+        "super" => words.push(Word::Key(Keyword::Super(
+            line[1].to_owned(),
+            line.get(2) == Some(&"pop"),
+        ))),
+        "with" => {
+            let items: Vec<_> = line.into_iter().skip(1).map(ToOwned::to_owned).collect();
+            words.push(Word::Key(Keyword::With(
+                parse_bindings(&items).expect("invalid sasm with"),
+            )))
+        }
+        // catch [<type> <...>] [; finally]
+        "catch" => {
+            let mut types: Vec<_> = line.iter().skip(1).map(|x| x.to_string()).collect();
+            let has_finally = types.ends_with(&[";".to_owned(), "finally".to_owned()]);
+            if has_finally {
+                types.truncate(types.len() - 2);
+            }
+            words.push(Word::Key(Keyword::Catch(
+                types,
+                sasm_read_func(lines),
+                sasm_read_func(lines),
+                has_finally.then(|| sasm_read_func(lines)),
+            )))
+        }
         "objpush" => words.push(Word::Key(Keyword::ObjPush)),
         "objpop" => words.push(Word::Key(Keyword::ObjPop)),
         "func_of_Rust" => {
@@ -133,6 +341,22 @@ fn sasm_parse<'a>(line: &str, words: &mut Vec<Word>, lines: &mut impl Iterator<I
                 }
                 words.push(Word::Const(Value::Str(s)));
             }
+            "bool" => {
+                words.push(Word::Const(Value::Bool(
+                    line[2].parse().expect("invalid sasm const: const bool NAB"),
+                )));
+            }
+            "bigint" => {
+                words.push(Word::Const(Value::BigInt(
+                    BigInt::from_str_radix(line[2], 10)
+                        .expect("invalid sasm const: const bigint NAN"),
+                )));
+            }
+            "decimal" => {
+                words.push(Word::Const(Value::Decimal(
+                    Decimal::parse(line[2]).expect("invalid sasm const: const decimal NAN"),
+                )));
+            }
             "int" => {
                 words.push(Word::Const(Value::Int(
                     line[2].parse().expect("invalid sasm const: const int NAN"),
@@ -162,14 +386,18 @@ fn sasm_parse<'a>(line: &str, words: &mut Vec<Word>, lines: &mut impl Iterator<I
                         .expect("invalid sasm const: const double NAN"),
                 )));
             }
-            "func" => words.push(Word::Const(Value::Func(Arc::new(Func {
-                ret_count: line[2].parse().expect("invalid sasm const: const fun NAN"),
-                to_call: FuncImpl::SPL(sasm_read_func(lines)),
-                origin: Arc::new(Frame::dummy()),
-                fname: None,
-                name: "dyn".to_owned(),
-                run_as_base: false,
-            })))),
+            "func" => {
+                let signature = read_signature(line[2]).expect("invalid sasm const: const fun NAN");
+                words.push(Word::Const(Value::Func(Arc::new(Func {
+                    ret_count: signature.ret_count(),
+                    to_call: FuncImpl::SPL(sasm_read_func(lines)),
+                    origin: Arc::new(Frame::dummy()),
+                    fname: None,
+                    name: "dyn".to_owned(),
+                    run_as_base: false,
+                    signature: Some(signature),
+                }))))
+            }
             "null" => words.push(Word::Const(Value::Null)),
             "array" => panic!("invalid sasm const: array - not all Values can be consts!"),
             _ => panic!("invalid sasm const: {}", line[1]),
@@ -228,17 +456,48 @@ fn sasm_write_func(words: Words) -> String {
                     output += &x;
                     output += "\n";
                 }
+                Keyword::Const(name, value) => {
+                    output += &format!("constant {name}\n\t");
+                    output += sasm_write_func(value).replace("\n", "\n\t").trim_end();
+                    output += "\nend\n";
+                }
                 Keyword::Func(name, returns, text) => {
-                    output += &format!("func {name} {returns}\n\t");
+                    output += &format!("func {name} {}\n\t", write_signature(&returns));
                     let text = sasm_write_func(text).replace("\n", "\n\t");
                     let text = text.trim_end();
-                    output += &text;
+                    output += text;
                     output += "\nend\n";
                 }
+                Keyword::Interface(name, requires, methods) => {
+                    output += &format!("interface {name} ");
+                    for (method, signature) in &requires {
+                        output += method;
+                        if let Some(signature) = signature {
+                            output += ":";
+                            output += &write_signature(signature);
+                        }
+                        output += " ";
+                    }
+                    output += ";";
+                    for method in &methods {
+                        output += " ";
+                        output += &method.0;
+                        output += " ";
+                        output += &write_signature(&method.1 .0);
+                    }
+                    output += "\n";
+                    for method in methods {
+                        output += "\t";
+                        output += sasm_write_func(method.1 .1)
+                            .replace("\n", "\n\t")
+                            .trim_end();
+                        output += "\nend\n";
+                    }
+                }
                 Keyword::Construct(name, vars, methods, is_namespace) => {
                     output += &format!("construct {name} ");
-                    for var in vars {
-                        output += &var;
+                    for var in &vars {
+                        output += &write_field(var);
                         output += " ";
                     }
                     output += ";";
@@ -246,7 +505,7 @@ fn sasm_write_func(words: Words) -> String {
                         output += " ";
                         output += &method.0;
                         output += " ";
-                        output += &method.1 .0.to_string();
+                        output += &write_signature(&method.1 .0);
                     }
                     if is_namespace {
                         output += " ; namespace";
@@ -254,11 +513,55 @@ fn sasm_write_func(words: Words) -> String {
                     output += "\n";
                     for method in methods {
                         output += "\t";
-                        output += &sasm_write_func(method.1 .1)
+                        output += sasm_write_func(method.1 .1)
+                            .replace("\n", "\n\t")
+                            .trim_end();
+                        output += "\nend\n";
+                    }
+                    for default in vars.into_iter().filter_map(|x| x.default) {
+                        output += "\t";
+                        output += sasm_write_func(default).replace("\n", "\n\t").trim_end();
+                        output += "\nend\n";
+                    }
+                }
+                Keyword::Enum(name, variants, methods) => {
+                    output += &format!("enum {name} ");
+                    for (variant, fields) in &variants {
+                        output += variant;
+                        output += " ";
+                        if !fields.is_empty() {
+                            output += "( ";
+                            for field in fields {
+                                output += &write_field(field);
+                                output += " ";
+                            }
+                            output += ") ";
+                        }
+                    }
+                    output += ";";
+                    for method in &methods {
+                        output += " ";
+                        output += &method.0;
+                        output += " ";
+                        output += &write_signature(&method.1 .0);
+                    }
+                    output += "\n";
+                    for method in methods {
+                        output += "\t";
+                        output += sasm_write_func(method.1 .1)
                             .replace("\n", "\n\t")
                             .trim_end();
                         output += "\nend\n";
                     }
+                    for default in variants
+                        .into_iter()
+                        .flat_map(|x| x.1)
+                        .filter_map(|x| x.default)
+                    {
+                        output += "\t";
+                        output += sasm_write_func(default).replace("\n", "\n\t").trim_end();
+                        output += "\nend\n";
+                    }
                 }
                 Keyword::Include(type_to_include, t) => {
                     output += &format!("include {type_to_include} {t}\n");
@@ -266,35 +569,84 @@ fn sasm_write_func(words: Words) -> String {
                 Keyword::Use(path) => output += &format!("use {path}\n"),
                 Keyword::While(cond, blk) => {
                     output += "while\n\t";
-                    output += &sasm_write_func(cond).replace("\n", "\n\t").trim_end();
+                    output += sasm_write_func(cond).replace("\n", "\n\t").trim_end();
                     output += "\nend\n\t";
-                    output += &sasm_write_func(blk).replace("\n", "\n\t").trim_end();
+                    output += sasm_write_func(blk).replace("\n", "\n\t").trim_end();
                     output += "\nend\n";
                 }
                 Keyword::If(blk) => {
                     output += "if\n\t";
-                    output += &sasm_write_func(blk).replace("\n", "\n\t").trim_end();
+                    output += sasm_write_func(blk).replace("\n", "\n\t").trim_end();
                     output += "\nend\n";
                 }
+                Keyword::IfElse(blk, els) => {
+                    output += "ifelse\n\t";
+                    output += sasm_write_func(blk).replace("\n", "\n\t").trim_end();
+                    output += "\nend\n\t";
+                    output += sasm_write_func(els).replace("\n", "\n\t").trim_end();
+                    output += "\nend\n";
+                }
+                Keyword::Match(arms, default) => {
+                    output += &format!("match {}", arms.len());
+                    if default.is_some() {
+                        output += " default";
+                    }
+                    for (i, (pattern, _)) in arms.iter().enumerate() {
+                        if let Pattern::Destructure(_, names) = pattern {
+                            output += &format!(" {i}({})", names.join(","));
+                        }
+                    }
+                    for blk in arms
+                        .into_iter()
+                        .flat_map(|(pattern, blk)| [pattern.words().clone(), blk])
+                        .chain(default)
+                    {
+                        output += "\n\t";
+                        output += sasm_write_func(blk).replace("\n", "\n\t").trim_end();
+                        output += "\nend";
+                    }
+                    output += "\n";
+                }
+                Keyword::For(var, iterable, blk) => {
+                    output += &format!("for {var}\n\t");
+                    output += sasm_write_func(iterable).replace("\n", "\n\t").trim_end();
+                    output += "\nend\n\t";
+                    output += sasm_write_func(blk).replace("\n", "\n\t").trim_end();
+                    output += "\nend\n";
+                }
+                Keyword::Break => output += "break\n",
+                Keyword::Continue => output += "continue\n",
+                Keyword::Return => output += "return\n",
+                Keyword::Super(name, rem) => {
+                    output += &format!("super {name}{}\n", if rem { " pop" } else { "" })
+                }
                 Keyword::With(items) => {
                     output += "with";
                     for item in items {
                         output += " ";
-                        output += &item;
+                        output += &item.to_string();
                     }
                     output += "\n";
                 }
-                Keyword::Catch(kinds, blk, ctch) => {
+                Keyword::Catch(kinds, blk, ctch, finally) => {
                     output += "catch";
                     for kind in kinds {
                         output += " ";
                         output += &kind;
                     }
+                    if finally.is_some() {
+                        output += " ; finally";
+                    }
                     output += "\n\t";
-                    output += &sasm_write_func(blk).replace("\n", "\n\t").trim_end();
+                    output += sasm_write_func(blk).replace("\n", "\n\t").trim_end();
                     output += "\nend\n\t";
-                    output += &sasm_write_func(ctch).replace("\n", "\n\t").trim_end();
+                    output += sasm_write_func(ctch).replace("\n", "\n\t").trim_end();
                     output += "\nend\n";
+                    if let Some(finally) = finally {
+                        output += "\t";
+                        output += sasm_write_func(finally).replace("\n", "\n\t").trim_end();
+                        output += "\nend\n";
+                    }
                 }
                 Keyword::ObjPush => output += "objpush\n",
                 Keyword::ObjPop => output += "objpop\n",
@@ -318,9 +670,12 @@ fn sasm_write_func(words: Words) -> String {
             },
             Word::Const(item) => match item {
                 Value::Null => output += "const null\n",
+                Value::Bool(x) => output += &format!("const bool {x}\n"),
                 Value::Int(x) => output += &format!("const int {x}\n"),
                 Value::Long(x) => output += &format!("const long {x}\n"),
                 Value::Mega(x) => output += &format!("const mega {x}\n"),
+                Value::BigInt(x) => output += &format!("const bigint {x}\n"),
+                Value::Decimal(x) => output += &format!("const decimal {x}\n"),
                 Value::Float(x) => output += &format!("const float {x}\n"),
                 Value::Double(x) => output += &format!("const double {x}\n"),
                 Value::Func(x) => {
@@ -330,9 +685,14 @@ fn sasm_write_func(words: Words) -> String {
                         FuncImpl::SPL(x) => sasm_write_func(x.clone()).replace("\n", "\n\t"),
                     };
                     let text = text.trim_end();
-                    output += &format!("const func {}\n\t{}\nend\n", x.ret_count, text);
+                    let signature = match x.signature {
+                        Some(ref signature) => write_signature(signature),
+                        None => x.ret_count.to_string(),
+                    };
+                    output += &format!("const func {signature}\n\t{text}\nend\n");
                 }
                 Value::Array(_) => panic!("sasm can't write arrays"),
+                Value::Bytes(_) => panic!("sasm can't write bytes"),
                 Value::Str(text) => {
                     fn time() -> String {
                         SystemTime::now()
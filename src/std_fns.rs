@@ -1,15 +1,17 @@
 use std::{
+    cmp::Ordering,
     collections::VecDeque,
     env::{args, vars},
     fs,
     io::{stdin, stdout, Write},
-    mem,
-    ops::{Add, Div, Mul, Rem, Sub},
     process::{self, Stdio},
     sync::Arc,
+    time::Instant,
 };
 
-use crate::{dyn_fns, mutex::Mut, runtime::*, sasm::sasm_write, *};
+use once_cell::sync::Lazy;
+
+use crate::{bignum::*, dyn_fns, mutex::Mut, runtime::*, sasm::sasm_write, *};
 
 #[macro_export]
 macro_rules! type_err {
@@ -29,6 +31,17 @@ macro_rules! array {
     };
 }
 
+macro_rules! bytes {
+    ($stack:expr, $i:expr) => {
+        || {
+            $stack.error(ErrorKind::PropertyNotFound(
+                "bytes".to_owned(),
+                $i.to_string(),
+            ))
+        }
+    };
+}
+
 pub fn print(stack: &mut Stack) -> OError {
     let Value::Str(s) = stack.pop().lock_ro().native.clone() else {
         return stack.err(ErrorKind::InvalidCall("print".to_owned()))
@@ -38,9 +51,27 @@ pub fn print(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+/// Copies the object. The copy is never frozen.
 pub fn clone(stack: &mut Stack) -> OError {
     let o = stack.pop();
-    stack.push(Arc::new(Mut::new(o.lock_ro().clone())));
+    let mut o = o.lock_ro().clone();
+    o.frozen = false;
+    stack.push(Arc::new(Mut::new(o)));
+    Ok(())
+}
+
+/// Makes the object read-only: its fields can no longer be set, and the contents of arrays and
+/// bytes no longer changed. Objects it refers to are not frozen with it.
+pub fn freeze(stack: &mut Stack) -> OError {
+    let o = stack.pop();
+    o.lock().frozen = true;
+    stack.push(o);
+    Ok(())
+}
+
+pub fn frozen(stack: &mut Stack) -> OError {
+    let frozen = stack.pop().lock_ro().frozen;
+    stack.push(Value::Bool(frozen).spl());
     Ok(())
 }
 
@@ -85,10 +116,7 @@ pub fn settype(stack: &mut Stack) -> OError {
     let o = stack.pop();
     let kind = runtime(|rt| rt.get_type_by_name(&s))
         .ok_or_else(|| stack.error(ErrorKind::TypeNotFound(s)))?;
-    let mut obj = o.lock();
-    kind.lock_ro().write_into(&mut obj);
-    obj.kind = kind;
-    mem::drop(obj);
+    stack.set_type(&o, kind)?;
     stack.push(o);
     Ok(())
 }
@@ -99,6 +127,38 @@ pub fn gettype(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+pub fn mro(stack: &mut Stack) -> OError {
+    require_on_stack!(name, Str, stack, "mro");
+    let kind = runtime(|rt| rt.get_type_by_name(&name))
+        .ok_or_else(|| stack.error(ErrorKind::TypeNotFound(name)))?;
+    let mro = kind.lock_ro().get_mro();
+    stack.push(
+        Value::Array(
+            [kind]
+                .into_iter()
+                .chain(mro)
+                .map(|x| Value::Str(x.lock_ro().get_name()).spl())
+                .collect(),
+        )
+        .spl(),
+    );
+    Ok(())
+}
+
+pub fn implements(stack: &mut Stack) -> OError {
+    require_on_stack!(name, Str, stack, "implements?");
+    let o = stack.pop();
+    let t = runtime(|rt| rt.get_type_by_name(&name))
+        .ok_or_else(|| stack.error(ErrorKind::TypeNotFound(name)))?;
+    let kind = o.lock_ro().kind.clone();
+    // interfaces are implemented by defining their methods, even without including them.
+    let implements = kind == t
+        || kind.lock_ro().get_mro().contains(&t)
+        || (t.lock_ro().is_interface && kind.lock_ro().missing(&t.lock_ro()).is_none());
+    stack.push(Value::Bool(implements).spl());
+    Ok(())
+}
+
 pub fn array_new(stack: &mut Stack) -> OError {
     let Value::Mega(i) = stack.pop().lock_ro().native.clone() else {
         return stack.err(ErrorKind::InvalidCall("anew".to_owned()))
@@ -130,6 +190,7 @@ pub fn array_get(stack: &mut Stack) -> OError {
 
 pub fn array_set(stack: &mut Stack) -> OError {
     let binding = stack.pop();
+    binding.lock_ro().check_mutable(stack)?;
     let Value::Array(ref mut a) = binding.lock().native else {
         return stack.err(ErrorKind::InvalidCall("array-set".to_owned()))
     };
@@ -142,160 +203,399 @@ pub fn array_set(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+pub fn bytes_new(stack: &mut Stack) -> OError {
+    let Value::Mega(i) = stack.pop().lock_ro().native.clone() else {
+        return stack.err(ErrorKind::InvalidCall("bnew".to_owned()));
+    };
+    stack.push(Value::Bytes(vec![0; i as usize]).spl());
+    Ok(())
+}
+
+pub fn bytes_len(stack: &mut Stack) -> OError {
+    let binding = stack.pop();
+    let Value::Bytes(ref a) = binding.lock_ro().native else {
+        return stack.err(ErrorKind::InvalidCall("bytes-len".to_owned()));
+    };
+    stack.push(Value::Mega(a.len() as i128).spl());
+    Ok(())
+}
+
+pub fn bytes_get(stack: &mut Stack) -> OError {
+    let binding = stack.pop();
+    let Value::Bytes(ref a) = binding.lock_ro().native else {
+        return stack.err(ErrorKind::InvalidCall("bytes-get".to_owned()));
+    };
+    let Value::Mega(i) = stack.pop().lock_ro().native.clone() else {
+        return stack.err(ErrorKind::InvalidCall("bytes-get".to_owned()));
+    };
+    stack.push(Value::Int(*a.get(i as usize).ok_or_else(bytes!(stack, i))? as i32).spl());
+    Ok(())
+}
+
+pub fn bytes_set(stack: &mut Stack) -> OError {
+    let binding = stack.pop();
+    binding.lock_ro().check_mutable(stack)?;
+    let Value::Bytes(ref mut a) = binding.lock().native else {
+        return stack.err(ErrorKind::InvalidCall("bytes-set".to_owned()));
+    };
+    let Value::Mega(i) = stack.pop().lock_ro().native.clone() else {
+        return stack.err(ErrorKind::InvalidCall("bytes-set".to_owned()));
+    };
+    require_int_on_stack!(o, stack, "bytes-set");
+    stack.push(Value::Int(*a.get(i as usize).ok_or_else(bytes!(stack, i))? as i32).spl());
+    *a.get_mut(i as usize).ok_or_else(bytes!(stack, i))? = o as u8;
+    Ok(())
+}
+
+pub fn bytes_sub(stack: &mut Stack) -> OError {
+    let binding = stack.pop();
+    let Value::Bytes(ref a) = binding.lock_ro().native else {
+        return stack.err(ErrorKind::InvalidCall("bytes-sub".to_owned()));
+    };
+    require_on_stack!(end, Mega, stack, "bytes-sub");
+    require_on_stack!(begin, Mega, stack, "bytes-sub");
+    if begin < 0 || begin > end {
+        return stack.err(ErrorKind::PropertyNotFound(
+            "bytes".to_owned(),
+            begin.to_string(),
+        ));
+    }
+    let sub = a
+        .get(begin as usize..end as usize)
+        .ok_or_else(bytes!(stack, end))?;
+    stack.push(Value::Bytes(sub.to_vec()).spl());
+    Ok(())
+}
+
+pub fn bytes_concat(stack: &mut Stack) -> OError {
+    require_on_stack!(b, Bytes, stack, "bytes-concat");
+    require_on_stack!(a, Bytes, stack, "bytes-concat");
+    stack.push(Value::Bytes([a, b].concat()).spl());
+    Ok(())
+}
+
+pub fn bytes_to_hex(stack: &mut Stack) -> OError {
+    require_on_stack!(a, Bytes, stack, "bytes-to-hex");
+    stack.push(Value::Str(a.iter().map(|x| format!("{x:02x}")).collect()).spl());
+    Ok(())
+}
+
+pub fn hex_to_bytes(stack: &mut Stack) -> OError {
+    require_on_stack!(s, Str, stack, "hex-to-bytes");
+    let parse_err = || stack.error(ErrorKind::Parse(s.clone(), "bytes".to_owned()));
+    if s.len() % 2 != 0 || !s.is_ascii() {
+        return Err(parse_err());
+    }
+    let mut bytes = Vec::with_capacity(s.len() / 2);
+    for i in (0..s.len()).step_by(2) {
+        bytes.push(u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| parse_err())?);
+    }
+    stack.push(Value::Bytes(bytes).spl());
+    Ok(())
+}
+
+/// Takes the offset, the size in bytes (at most 8), whether it is big endian, and the bytes, and
+/// pushes the unsigned integer stored there.
+pub fn bytes_read_uint(stack: &mut Stack) -> OError {
+    let binding = stack.pop();
+    let Value::Bytes(ref a) = binding.lock_ro().native else {
+        return stack.err(ErrorKind::InvalidCall("bytes-read-uint".to_owned()));
+    };
+    let big_endian = stack.pop().lock_ro().is_truthy();
+    require_on_stack!(size, Mega, stack, "bytes-read-uint");
+    require_on_stack!(offset, Mega, stack, "bytes-read-uint");
+    if !(1..=8).contains(&size) || offset < 0 {
+        return stack.err(ErrorKind::InvalidCall("bytes-read-uint".to_owned()));
+    }
+    let mut buf = [0; 8];
+    let src = a
+        .get(offset as usize..(offset + size) as usize)
+        .ok_or_else(bytes!(stack, offset + size - 1))?;
+    let x = if big_endian {
+        buf[8 - size as usize..].copy_from_slice(src);
+        u64::from_be_bytes(buf)
+    } else {
+        buf[..size as usize].copy_from_slice(src);
+        u64::from_le_bytes(buf)
+    };
+    stack.push(Value::Mega(x as i128).spl());
+    Ok(())
+}
+
+/// Takes the value, the offset, the size in bytes (at most 8), whether it is big endian, and the
+/// bytes, and stores the lowest bytes of the value there.
+pub fn bytes_write_uint(stack: &mut Stack) -> OError {
+    let binding = stack.pop();
+    binding.lock_ro().check_mutable(stack)?;
+    let Value::Bytes(ref mut a) = binding.lock().native else {
+        return stack.err(ErrorKind::InvalidCall("bytes-write-uint".to_owned()));
+    };
+    let big_endian = stack.pop().lock_ro().is_truthy();
+    require_on_stack!(size, Mega, stack, "bytes-write-uint");
+    require_on_stack!(offset, Mega, stack, "bytes-write-uint");
+    require_on_stack!(x, Mega, stack, "bytes-write-uint");
+    if !(1..=8).contains(&size) || offset < 0 {
+        return stack.err(ErrorKind::InvalidCall("bytes-write-uint".to_owned()));
+    }
+    let dest = a
+        .get_mut(offset as usize..(offset + size) as usize)
+        .ok_or_else(bytes!(stack, offset + size - 1))?;
+    if big_endian {
+        dest.copy_from_slice(&(x as u64).to_be_bytes()[8 - size as usize..]);
+    } else {
+        dest.copy_from_slice(&(x as u64).to_le_bytes()[..size as usize]);
+    }
+    Ok(())
+}
+
+/// Calls the operator method of the type of `a`, the left operand, like `op-add`, with `b` as its
+/// argument, if `a` is a construct and its type has one. Returns whether it did.
+fn call_operator(
+    stack: &mut Stack,
+    method: &'static str,
+    a: &AMObject,
+    b: &AMObject,
+) -> Result<bool, Error> {
+    if !a.lock_ro().is_construct() {
+        return Ok(false);
+    }
+    let Some(f) = a.lock_ro().kind.lock_ro().get_fn(method.to_owned()) else {
+        return Ok(false);
+    };
+    let height = stack.len();
+    stack.push(b.clone());
+    stack.push(a.clone());
+    stack.call(&f)?;
+    if stack.len() != height + 1 {
+        return Err(stack.error(ErrorKind::InvalidCall(method.to_owned())));
+    }
+    Ok(true)
+}
+
 pub fn eq(stack: &mut Stack) -> OError {
     let b = stack.pop();
     let a = stack.pop();
-    stack.push(Value::Int(if a == b { 1 } else { 0 }).spl());
+    // comparing with null checks for absence and never calls op-eq
+    let null = *b.lock_ro() == Value::Null.into();
+    if !null && call_operator(stack, "op-eq", &a, &b)? {
+        return Ok(());
+    }
+    let equal = match compare_big(&a, &b) {
+        Some(ordering) => ordering == Ordering::Equal,
+        None => a == b,
+    };
+    stack.push(Value::Bool(equal).spl());
     Ok(())
 }
 
 pub fn lt(stack: &mut Stack) -> OError {
     let b = stack.pop();
     let a = stack.pop();
-    stack.push(Value::Int(if a < b { 1 } else { 0 }).spl());
+    if call_operator(stack, "op-lt", &a, &b)? {
+        return Ok(());
+    }
+    let less = compare(stack, "lt", &a, &b)? == Ordering::Less;
+    stack.push(Value::Bool(less).spl());
     Ok(())
 }
 
 pub fn gt(stack: &mut Stack) -> OError {
     let b = stack.pop();
     let a = stack.pop();
-    stack.push(Value::Int(if a > b { 1 } else { 0 }).spl());
+    if call_operator(stack, "op-gt", &a, &b)? {
+        return Ok(());
+    }
+    let greater = compare(stack, "gt", &a, &b)? == Ordering::Greater;
+    stack.push(Value::Bool(greater).spl());
     Ok(())
 }
 
 pub fn not(stack: &mut Stack) -> OError {
     let o = stack.pop();
-    stack.push(Value::Int(if o.lock_ro().is_truthy() { 0 } else { 1 }).spl());
+    stack.push(Value::Bool(!o.lock_ro().is_truthy()).spl());
     Ok(())
 }
 
 pub fn and(stack: &mut Stack) -> OError {
     let a = stack.pop();
     let b = stack.pop();
-    stack.push(
-        Value::Int(if a.lock_ro().is_truthy() && b.lock_ro().is_truthy() {
-            1
-        } else {
-            0
-        })
-        .spl(),
-    );
+    stack.push(Value::Bool(a.lock_ro().is_truthy() && b.lock_ro().is_truthy()).spl());
     Ok(())
 }
 
 pub fn or(stack: &mut Stack) -> OError {
     let a = stack.pop();
     let b = stack.pop();
-    stack.push(
-        Value::Int(if a.lock_ro().is_truthy() || b.lock_ro().is_truthy() {
-            1
-        } else {
-            0
-        })
-        .spl(),
-    );
+    stack.push(Value::Bool(a.lock_ro().is_truthy() || b.lock_ro().is_truthy()).spl());
     Ok(())
 }
 
-macro_rules! impl_op {
-    ($a:expr, $b:expr, $op:tt, $err:expr, $($kind:tt,)*) => {
-        match ($a, $b) {
-            $(
-                (Value::$kind(a), Value::$kind(b)) => Value::$kind(a.$op(b)),
-            )*
-            _ => $err?,
+/// One of the arithmetic natives.
+#[derive(Clone, Copy)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+macro_rules! checked_op {
+    ($op:expr, $a:expr, $b:expr) => {
+        match $op {
+            Op::Add => $a.checked_add($b),
+            Op::Sub => $a.checked_sub($b),
+            Op::Mul => $a.checked_mul($b),
+            Op::Div => $a.checked_div($b),
+            Op::Rem => $a.checked_rem($b),
         }
     };
 }
 
-pub fn plus(stack: &mut Stack) -> OError {
-    let b = stack.pop().lock_ro().native.clone();
-    let a = stack.pop().lock_ro().native.clone();
-    stack.push(
-        impl_op!(
-            a,
-            b,
-            add,
-            stack.err(ErrorKind::InvalidCall("plus".to_owned())),
-            Mega,
-            Long,
-            Int,
-        )
-        .spl(),
-    );
+fn as_bigint(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Int(x) => Some(BigInt::from(*x as i128)),
+        Value::Long(x) => Some(BigInt::from(*x as i128)),
+        Value::Mega(x) => Some(BigInt::from(*x)),
+        Value::BigInt(x) => Some(x.clone()),
+        _ => None,
+    }
+}
+
+fn as_decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Decimal(x) => Some(x.clone()),
+        x => as_bigint(x).map(Decimal::from),
+    }
+}
+
+fn is_zero(value: &Value) -> bool {
+    match value {
+        Value::Int(x) => *x == 0,
+        Value::Long(x) => *x == 0,
+        Value::Mega(x) => *x == 0,
+        Value::BigInt(x) => x.is_zero(),
+        Value::Decimal(x) => x.is_zero(),
+        _ => false,
+    }
+}
+
+/// Compares two values for lt and gt, throwing InvalidCall if they can't be compared.
+fn compare(stack: &Stack, name: &str, a: &AMObject, b: &AMObject) -> Result<Ordering, Error> {
+    if let Some(ordering) = compare_big(a, b) {
+        return Ok(ordering);
+    }
+    let ordering = a.lock_ro().partial_cmp(&b.lock_ro());
+    ordering.ok_or_else(|| stack.error(ErrorKind::InvalidCall(name.to_owned())))
+}
+
+/// Compares two numbers by their value if at least one of them is a bigint or a decimal.
+fn compare_big(a: &AMObject, b: &AMObject) -> Option<Ordering> {
+    let (a, b) = (&a.lock_ro().native, &b.lock_ro().native);
+    let is_big = |x: &Value| matches!(x, Value::BigInt(_) | Value::Decimal(_));
+    if !is_big(a) && !is_big(b) {
+        return None;
+    }
+    Some(as_decimal(a)?.cmp(&as_decimal(b)?))
+}
+
+/// Returns None when dividing by zero.
+fn bigint_op(op: Op, a: BigInt, b: BigInt) -> Option<BigInt> {
+    Some(match op {
+        Op::Add => a + b,
+        Op::Sub => a - b,
+        Op::Mul => a * b,
+        Op::Div => a.div_rem(&b)?.0,
+        Op::Rem => a.div_rem(&b)?.1,
+    })
+}
+
+/// Returns None when dividing by zero.
+fn decimal_op(op: Op, a: Decimal, b: Decimal) -> Option<Decimal> {
+    Some(match op {
+        Op::Add => a + b,
+        Op::Sub => a - b,
+        Op::Mul => a * b,
+        Op::Div => a.checked_div(&b)?,
+        Op::Rem => a.checked_rem(&b)?,
+    })
+}
+
+/// Calculates with two numbers of the same type, or with a bigint or a decimal and an integer,
+/// which is converted to the type of the other one first. int and long fail with an Overflow if
+/// the result doesn't fit, mega is promoted to bigint instead.
+fn arithmetic(stack: &mut Stack, op: Op, name: &str) -> OError {
+    let b = stack.pop();
+    let a = stack.pop();
+    let method = match op {
+        Op::Add => "op-add",
+        Op::Sub => "op-sub",
+        Op::Mul => "op-mul",
+        Op::Div => "op-div",
+        Op::Rem => "op-mod",
+    };
+    if call_operator(stack, method, &a, &b)? {
+        return Ok(());
+    }
+    let b = b.lock_ro().native.clone();
+    let a = a.lock_ro().native.clone();
+    let division_by_zero = || stack.error(ErrorKind::DivisionByZero(name.to_owned()));
+    let overflow = || stack.error(ErrorKind::Overflow(name.to_owned()));
+    if matches!(op, Op::Div | Op::Rem) && is_zero(&b) && as_decimal(&a).is_some() {
+        return Err(division_by_zero());
+    }
+    let result = match (&a, &b) {
+        (Value::Int(a), Value::Int(b)) => Value::Int(checked_op!(op, a, *b).ok_or_else(overflow)?),
+        (Value::Long(a), Value::Long(b)) => {
+            Value::Long(checked_op!(op, a, *b).ok_or_else(overflow)?)
+        }
+        (Value::Mega(a), Value::Mega(b)) => match checked_op!(op, a, *b) {
+            Some(x) => Value::Mega(x),
+            None => Value::BigInt(
+                bigint_op(op, BigInt::from(*a), BigInt::from(*b)).ok_or_else(division_by_zero)?,
+            ),
+        },
+        (Value::Decimal(_), _) | (_, Value::Decimal(_)) => match (as_decimal(&a), as_decimal(&b)) {
+            (Some(a), Some(b)) => {
+                Value::Decimal(decimal_op(op, a, b).ok_or_else(division_by_zero)?)
+            }
+            _ => return stack.err(ErrorKind::InvalidCall(name.to_owned())),
+        },
+        (Value::BigInt(_), _) | (_, Value::BigInt(_)) => match (as_bigint(&a), as_bigint(&b)) {
+            (Some(a), Some(b)) => Value::BigInt(bigint_op(op, a, b).ok_or_else(division_by_zero)?),
+            _ => return stack.err(ErrorKind::InvalidCall(name.to_owned())),
+        },
+        _ => return stack.err(ErrorKind::InvalidCall(name.to_owned())),
+    };
+    stack.push(result.spl());
     Ok(())
 }
 
+pub fn plus(stack: &mut Stack) -> OError {
+    arithmetic(stack, Op::Add, "plus")
+}
+
 pub fn minus(stack: &mut Stack) -> OError {
-    let b = stack.pop().lock_ro().native.clone();
-    let a = stack.pop().lock_ro().native.clone();
-    stack.push(
-        impl_op!(
-            a,
-            b,
-            sub,
-            stack.err(ErrorKind::InvalidCall("minus".to_owned())),
-            Mega,
-            Long,
-            Int,
-        )
-        .spl(),
-    );
-    Ok(())
+    arithmetic(stack, Op::Sub, "minus")
 }
 
 pub fn slash(stack: &mut Stack) -> OError {
-    let b = stack.pop().lock_ro().native.clone();
-    let a = stack.pop().lock_ro().native.clone();
-    stack.push(
-        impl_op!(
-            a,
-            b,
-            div,
-            stack.err(ErrorKind::InvalidCall("slash".to_owned())),
-            Mega,
-            Long,
-            Int,
-        )
-        .spl(),
-    );
-    Ok(())
+    arithmetic(stack, Op::Div, "slash")
 }
 
 pub fn star(stack: &mut Stack) -> OError {
-    let b = stack.pop().lock_ro().native.clone();
-    let a = stack.pop().lock_ro().native.clone();
-    stack.push(
-        impl_op!(
-            a,
-            b,
-            mul,
-            stack.err(ErrorKind::InvalidCall("star".to_owned())),
-            Mega,
-            Long,
-            Int,
-        )
-        .spl(),
-    );
-    Ok(())
+    arithmetic(stack, Op::Mul, "star")
 }
 
 pub fn percent(stack: &mut Stack) -> OError {
-    let b = stack.pop().lock_ro().native.clone();
-    let a = stack.pop().lock_ro().native.clone();
-    stack.push(
-        impl_op!(
-            a,
-            b,
-            rem,
-            stack.err(ErrorKind::InvalidCall("star".to_owned())),
-            Mega,
-            Long,
-            Int,
-        )
-        .spl(),
-    );
-    Ok(())
+    arithmetic(stack, Op::Rem, "percent")
+}
+
+/// Converts a bigint to a fixed-size integer, failing with an Overflow if it doesn't fit.
+fn big_to_fixed<T: TryFrom<i128>>(stack: &Stack, x: &BigInt, name: &str) -> Result<T, Error> {
+    x.to_i128()
+        .and_then(|x| x.try_into().ok())
+        .ok_or_else(|| stack.error(ErrorKind::Overflow(name.to_owned())))
 }
 
 pub fn to_int(stack: &mut Stack) -> OError {
@@ -308,8 +608,12 @@ pub fn to_int(stack: &mut Stack) -> OError {
             Value::Mega(x) => x as i32,
             Value::Float(x) => x as i32,
             Value::Double(x) => x as i32,
+            Value::Bool(x) => x as i32,
             Value::Func(_) => type_err!(stack, "func", "int"),
             Value::Array(_) => type_err!(stack, "array", "int"),
+            Value::Bytes(_) => type_err!(stack, "bytes", "int"),
+            Value::BigInt(x) => big_to_fixed(stack, &x, "_int")?,
+            Value::Decimal(x) => big_to_fixed(stack, &x.trunc(), "_int")?,
             Value::Str(x) => x
                 .parse()
                 .map_err(|_| stack.error(ErrorKind::Parse(x, "int".to_owned())))?,
@@ -329,8 +633,12 @@ pub fn to_long(stack: &mut Stack) -> OError {
             Value::Mega(x) => x as i64,
             Value::Float(x) => x as i64,
             Value::Double(x) => x as i64,
+            Value::Bool(x) => x as i64,
             Value::Func(_) => type_err!(stack, "func", "long"),
             Value::Array(_) => type_err!(stack, "array", "long"),
+            Value::Bytes(_) => type_err!(stack, "bytes", "long"),
+            Value::BigInt(x) => big_to_fixed(stack, &x, "_long")?,
+            Value::Decimal(x) => big_to_fixed(stack, &x.trunc(), "_long")?,
             Value::Str(x) => x
                 .parse()
                 .map_err(|_| stack.error(ErrorKind::Parse(x, "long".to_owned())))?,
@@ -350,8 +658,12 @@ pub fn to_mega(stack: &mut Stack) -> OError {
             Value::Mega(x) => x,
             Value::Float(x) => x as i128,
             Value::Double(x) => x as i128,
+            Value::Bool(x) => x as i128,
             Value::Func(_) => type_err!(stack, "func", "mega"),
             Value::Array(_) => type_err!(stack, "array", "mega"),
+            Value::Bytes(_) => type_err!(stack, "bytes", "mega"),
+            Value::BigInt(x) => big_to_fixed(stack, &x, "_mega")?,
+            Value::Decimal(x) => big_to_fixed(stack, &x.trunc(), "_mega")?,
             Value::Str(x) => x
                 .parse()
                 .map_err(|_| stack.error(ErrorKind::Parse(x, "mega".to_owned())))?,
@@ -371,8 +683,12 @@ pub fn to_float(stack: &mut Stack) -> OError {
             Value::Mega(x) => x as f32,
             Value::Float(x) => x,
             Value::Double(x) => x as f32,
+            Value::Bool(x) => x as u8 as f32,
             Value::Func(_) => type_err!(stack, "func", "float"),
             Value::Array(_) => type_err!(stack, "array", "float"),
+            Value::Bytes(_) => type_err!(stack, "bytes", "float"),
+            Value::BigInt(x) => x.to_f64() as f32,
+            Value::Decimal(x) => x.to_f64() as f32,
             Value::Str(x) => x
                 .parse()
                 .map_err(|_| stack.error(ErrorKind::Parse(x, "float".to_owned())))?,
@@ -392,8 +708,12 @@ pub fn to_double(stack: &mut Stack) -> OError {
             Value::Mega(x) => x as f64,
             Value::Float(x) => x as f64,
             Value::Double(x) => x,
+            Value::Bool(x) => x as u8 as f64,
             Value::Func(_) => type_err!(stack, "func", "double"),
             Value::Array(_) => type_err!(stack, "array", "double"),
+            Value::Bytes(_) => type_err!(stack, "bytes", "double"),
+            Value::BigInt(x) => x.to_f64(),
+            Value::Decimal(x) => x.to_f64(),
             Value::Str(x) => x
                 .parse()
                 .map_err(|_| stack.error(ErrorKind::Parse(x, "double".to_owned())))?,
@@ -403,6 +723,78 @@ pub fn to_double(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+/// Floats and decimals are rounded towards zero, strings are parsed.
+pub fn to_bigint(stack: &mut Stack) -> OError {
+    let o = stack.pop().lock_ro().native.clone();
+    let float = |stack: &Stack, x: String| match Decimal::parse(&x) {
+        Some(x) => Ok(x.trunc()),
+        None => stack.err(ErrorKind::Parse(x, "bigint".to_owned())),
+    };
+    stack.push(
+        Value::BigInt(match o {
+            Value::Null => type_err!(stack, "null", "bigint"),
+            Value::Int(x) => BigInt::from(x as i128),
+            Value::Long(x) => BigInt::from(x as i128),
+            Value::Mega(x) => BigInt::from(x),
+            Value::Float(x) => float(stack, x.to_string())?,
+            Value::Double(x) => float(stack, x.to_string())?,
+            Value::Bool(x) => BigInt::from(x as i128),
+            Value::Func(_) => type_err!(stack, "func", "bigint"),
+            Value::Array(_) => type_err!(stack, "array", "bigint"),
+            Value::Bytes(_) => type_err!(stack, "bytes", "bigint"),
+            Value::BigInt(x) => x,
+            Value::Decimal(x) => x.trunc(),
+            Value::Str(x) => match BigInt::from_str_radix(&x, 10) {
+                Some(x) => x,
+                None => stack.err(ErrorKind::Parse(x, "bigint".to_owned()))?,
+            },
+        })
+        .spl(),
+    );
+    Ok(())
+}
+
+/// Floats are converted using their shortest exact representation, strings are parsed.
+pub fn to_decimal(stack: &mut Stack) -> OError {
+    let o = stack.pop().lock_ro().native.clone();
+    let parse = |stack: &Stack, x: String| match Decimal::parse(&x) {
+        Some(x) => Ok(x),
+        None => stack.err(ErrorKind::Parse(x, "decimal".to_owned())),
+    };
+    stack.push(
+        Value::Decimal(match o {
+            Value::Null => type_err!(stack, "null", "decimal"),
+            Value::Int(x) => BigInt::from(x as i128).into(),
+            Value::Long(x) => BigInt::from(x as i128).into(),
+            Value::Mega(x) => BigInt::from(x).into(),
+            Value::Float(x) => parse(stack, x.to_string())?,
+            Value::Double(x) => parse(stack, x.to_string())?,
+            Value::Bool(x) => BigInt::from(x as i128).into(),
+            Value::Func(_) => type_err!(stack, "func", "decimal"),
+            Value::Array(_) => type_err!(stack, "array", "decimal"),
+            Value::Bytes(_) => type_err!(stack, "bytes", "decimal"),
+            Value::BigInt(x) => x.into(),
+            Value::Decimal(x) => x,
+            Value::Str(x) => parse(stack, x)?,
+        })
+        .spl(),
+    );
+    Ok(())
+}
+
+/// Strings are parsed, everything else is converted by its truthiness.
+pub fn to_bool(stack: &mut Stack) -> OError {
+    let o = stack.pop();
+    let b = match &o.lock_ro().native {
+        Value::Str(x) => x
+            .parse()
+            .map_err(|_| stack.error(ErrorKind::Parse(x.clone(), "bool".to_owned())))?,
+        _ => o.lock_ro().is_truthy(),
+    };
+    stack.push(Value::Bool(b).spl());
+    Ok(())
+}
+
 pub fn to_array(stack: &mut Stack) -> OError {
     let o = stack.pop().lock_ro().native.clone();
     stack.push(
@@ -413,8 +805,12 @@ pub fn to_array(stack: &mut Stack) -> OError {
             Value::Mega(_) => type_err!(stack, "mega", "array"),
             Value::Float(_) => type_err!(stack, "float", "array"),
             Value::Double(_) => type_err!(stack, "double", "array"),
+            Value::BigInt(_) => type_err!(stack, "bigint", "array"),
+            Value::Decimal(_) => type_err!(stack, "decimal", "array"),
+            Value::Bool(_) => type_err!(stack, "bool", "array"),
             Value::Func(_) => type_err!(stack, "func", "array"),
             Value::Array(x) => x,
+            Value::Bytes(x) => x.into_iter().map(|x| Value::Int(x as i32).spl()).collect(),
             Value::Str(x) => x
                 .chars()
                 .map(|x| Value::Int(x as u32 as i32).spl())
@@ -425,8 +821,56 @@ pub fn to_array(stack: &mut Stack) -> OError {
     Ok(())
 }
 
-pub fn to_str(stack: &mut Stack) -> OError {
+/// Strings are encoded as UTF-8, arrays must contain only ints.
+pub fn to_bytes(stack: &mut Stack) -> OError {
     let o = stack.pop().lock_ro().native.clone();
+    stack.push(
+        Value::Bytes(match o {
+            Value::Null => type_err!(stack, "null", "bytes"),
+            Value::Int(_) => type_err!(stack, "int", "bytes"),
+            Value::Long(_) => type_err!(stack, "long", "bytes"),
+            Value::Mega(_) => type_err!(stack, "mega", "bytes"),
+            Value::Float(_) => type_err!(stack, "float", "bytes"),
+            Value::Double(_) => type_err!(stack, "double", "bytes"),
+            Value::BigInt(_) => type_err!(stack, "bigint", "bytes"),
+            Value::Decimal(_) => type_err!(stack, "decimal", "bytes"),
+            Value::Bool(_) => type_err!(stack, "bool", "bytes"),
+            Value::Func(_) => type_err!(stack, "func", "bytes"),
+            Value::Array(x) => {
+                let mut fixed = Vec::with_capacity(x.len());
+                for item in x {
+                    match item.lock_ro().native.clone().try_mega_to_int() {
+                        Value::Int(x) => fixed.push(x as u8),
+                        _ => type_err!(stack, "!int", "__bytes-element"),
+                    }
+                }
+                fixed
+            }
+            Value::Bytes(x) => x,
+            Value::Str(x) => x.into_bytes(),
+        })
+        .spl(),
+    );
+    Ok(())
+}
+
+pub fn to_str(stack: &mut Stack) -> OError {
+    let o = stack.pop();
+    if o.lock_ro().is_construct() {
+        let f = o.lock_ro().kind.lock_ro().get_fn("to-str".to_owned());
+        match f {
+            Some(f) => {
+                stack.push(o);
+                stack.call(&f)?;
+            }
+            None => {
+                let s = stack.debug_str(&o)?;
+                stack.push(Value::Str(s).spl());
+            }
+        }
+        return Ok(());
+    }
+    let o = o.lock_ro().native.clone();
     stack.push(
         Value::Str(match o {
             Value::Null => type_err!(stack, "null", "str"),
@@ -435,6 +879,10 @@ pub fn to_str(stack: &mut Stack) -> OError {
             Value::Mega(x) => x.to_string(),
             Value::Float(x) => x.to_string(),
             Value::Double(x) => x.to_string(),
+            Value::Bool(x) => x.to_string(),
+            Value::BigInt(x) => x.to_string(),
+            Value::Decimal(x) => x.to_string(),
+            Value::Bytes(x) => String::from_utf8_lossy(&x).into_owned(),
             Value::Func(_) => type_err!(stack, "func", "str"),
             Value::Array(x) => {
                 let iter: Vec<_> = x
@@ -465,6 +913,45 @@ pub fn to_str(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+pub fn format(stack: &mut Stack) -> OError {
+    let Value::Str(fmt) = stack.pop().lock_ro().native.clone() else {
+        return stack.err(ErrorKind::InvalidCall("format".to_owned()));
+    };
+    let Some(pieces) = parse_format(&fmt) else {
+        return stack.err(ErrorKind::Parse(fmt, "format-string".to_owned()));
+    };
+    let amount = pieces
+        .iter()
+        .filter(|x| matches!(x, FormatPiece::Arg(_)))
+        .count();
+    let mut args: VecDeque<_> = (0..amount).map(|_| stack.pop()).collect();
+    let mut s = String::new();
+    for piece in pieces {
+        match piece {
+            FormatPiece::Text(text) => s += &text,
+            FormatPiece::Arg(spec) => {
+                let mut arg = args.pop_back().unwrap();
+                // constructs are written the way _str writes them, using their to-str hook
+                if arg.lock_ro().is_construct() {
+                    stack.push(arg);
+                    to_str(stack)?;
+                    arg = stack.pop();
+                }
+                s += &spec.format(&arg.lock_ro());
+            }
+        }
+    }
+    stack.push(Value::Str(s).spl());
+    Ok(())
+}
+
+pub fn debug_str(stack: &mut Stack) -> OError {
+    let o = stack.pop();
+    let s = stack.debug_str(&o)?;
+    stack.push(Value::Str(s).spl());
+    Ok(())
+}
+
 pub fn call(stack: &mut Stack) -> OError {
     let Value::Func(a) = stack.pop().lock_ro().native.clone() else {
         return stack.err(ErrorKind::InvalidCall("call".to_owned()))
@@ -561,7 +1048,7 @@ pub fn stop(stack: &mut Stack) -> OError {
 }
 
 pub fn argv(stack: &mut Stack) -> OError {
-    stack.push(Value::Array(args().into_iter().map(|x| Value::Str(x).spl()).collect()).spl());
+    stack.push(Value::Array(args().map(|x| Value::Str(x).spl()).collect()).spl());
     Ok(())
 }
 
@@ -569,7 +1056,6 @@ pub fn get_env(stack: &mut Stack) -> OError {
     stack.push(
         Value::Array(
             vars()
-                .into_iter()
                 .map(|x| Value::Array(vec![Value::Str(x.0).spl(), Value::Str(x.1).spl()]).spl())
                 .collect(),
         )
@@ -604,7 +1090,7 @@ pub fn import(stack: &mut Stack) -> OError {
     };
     let fallback = s
         .as_str()
-        .rsplit_once(|x| x == '/' || x == '#')
+        .rsplit_once(['/', '#'])
         .map(|(.., x)| x)
         .unwrap_or(s.as_str());
     let fallback = runtime(|x| {
@@ -736,22 +1222,28 @@ pub fn command_wait(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+/// When time-millis counts from. An [Instant] keeps counting forward when the system clock is
+/// changed, so deadlines based on it stay correct.
+static START: Lazy<Instant> = Lazy::new(Instant::now);
+
+pub fn time_millis(stack: &mut Stack) -> OError {
+    stack.push(Value::Mega(START.elapsed().as_millis() as i128).spl());
+    Ok(())
+}
+
 pub fn str_to_bytes(stack: &mut Stack) -> OError {
     require_on_stack!(s, Str, stack, "str-to-bytes");
-    stack.push(
-        Value::Array(
-            s.bytes()
-                .into_iter()
-                .map(|x| Value::Int(x as i32).spl())
-                .collect(),
-        )
-        .spl(),
-    );
+    stack.push(Value::Bytes(s.into_bytes()).spl());
     Ok(())
 }
 
 pub fn bytes_to_str(stack: &mut Stack) -> OError {
-    require_array_on_stack!(a, stack, "str-to-bytes");
+    let binding = stack.pop();
+    if let Value::Bytes(ref a) = binding.lock_ro().native {
+        stack.push(Value::Str(String::from_utf8_lossy(a).into_owned()).spl());
+        return Ok(());
+    }
+    require_array!(a, binding, stack, "bytes-to-str");
     let mut chars = Vec::new();
     for item in a.iter() {
         if let Value::Int(x) = item.lock_ro().native.clone().try_mega_to_int() {
@@ -803,12 +1295,25 @@ pub fn throw(stack: &mut Stack) -> OError {
     stack.err(ErrorKind::CustomObject(obj))
 }
 
+/// Throws a caught error again, keeping its traces. Anything else is thrown like by throw.
+pub fn rethrow(stack: &mut Stack) -> OError {
+    let obj = stack.pop();
+    let error = Error::from_object(&obj);
+    match error {
+        Some(error) => Err(error),
+        None => {
+            stack.push(obj);
+            throw(stack)
+        }
+    }
+}
+
 pub fn write_sasm(stack: &mut Stack) -> OError {
     require_on_stack!(code, Str, stack, "write-sasm");
     stack.push(
         Value::Str(
             lexer::lex(code)
-                .map(|x| sasm_write(x))
+                .map(sasm_write)
                 .map_err(|x| stack.error(ErrorKind::LexError(format!("{x:?}"))))?,
         )
         .spl(),
@@ -823,7 +1328,7 @@ pub fn write_file_sasm(stack: &mut Stack) -> OError {
             lexer::lex(
                 fs::read_to_string(file).map_err(|x| stack.error(ErrorKind::IO(x.to_string())))?,
             )
-            .map(|x| sasm_write(x))
+            .map(sasm_write)
             .map_err(|x| stack.error(ErrorKind::LexError(format!("{x:?}"))))?,
         )
         .spl(),
@@ -833,19 +1338,33 @@ pub fn write_file_sasm(stack: &mut Stack) -> OError {
 
 pub fn register(r: &mut Stack, o: Arc<Frame>) {
     type Fn = fn(&mut Stack) -> OError;
-    let fns: [(&str, Fn, u32); 53] = [
+    let fns: [(&str, Fn, u32); 75] = [
         ("pop", pop, 0),
         ("dup", dup, 2),
         ("clone", clone, 1),
+        ("freeze", freeze, 1),
+        ("frozen?", frozen, 1),
         ("swap", swap, 2),
         ("mswap", mswap, 2),
         ("print", print, 0),
         ("gettype", gettype, 1),
         ("settype", settype, 1),
+        ("mro", mro, 1),
+        ("implements?", implements, 1),
         ("anew", array_new, 1),
         ("array-len", array_len, 1),
         ("array-get", array_get, 1),
         ("array-set", array_set, 1),
+        ("bnew", bytes_new, 1),
+        ("bytes-len", bytes_len, 1),
+        ("bytes-get", bytes_get, 1),
+        ("bytes-set", bytes_set, 1),
+        ("bytes-sub", bytes_sub, 1),
+        ("bytes-concat", bytes_concat, 1),
+        ("bytes-to-hex", bytes_to_hex, 1),
+        ("hex-to-bytes", hex_to_bytes, 1),
+        ("bytes-read-uint", bytes_read_uint, 1),
+        ("bytes-write-uint", bytes_write_uint, 0),
         ("eq", eq, 1),
         ("lt", lt, 1),
         ("gt", gt, 1),
@@ -862,8 +1381,14 @@ pub fn register(r: &mut Stack, o: Arc<Frame>) {
         ("_mega", to_mega, 1),
         ("_float", to_float, 1),
         ("_double", to_double, 1),
+        ("_bigint", to_bigint, 1),
+        ("_decimal", to_decimal, 1),
+        ("_bool", to_bool, 1),
         ("_array", to_array, 1),
+        ("_bytes", to_bytes, 1),
         ("_str", to_str, 1),
+        ("format", format, 1),
+        ("debug-str", debug_str, 1),
         ("call", call, 0),
         ("callp", callp, 0),
         ("trace", trace, 1),
@@ -881,10 +1406,12 @@ pub fn register(r: &mut Stack, o: Arc<Frame>) {
         ("readln", readln, 1),
         ("command", command, 0),
         ("command-wait", command_wait, 1),
+        ("time-millis", time_millis, 1),
         ("str-to-bytes", str_to_bytes, 1),
         ("bytes-to-str", bytes_to_str, 1),
         ("acopy", acopy, 1),
         ("throw", throw, 0),
+        ("rethrow", rethrow, 0),
         ("write-sasm", write_sasm, 1),
         ("write-file-sasm", write_file_sasm, 1),
     ];
@@ -895,6 +1422,7 @@ pub fn register(r: &mut Stack, o: Arc<Frame>) {
                 ret_count: f.2,
                 to_call: FuncImpl::Native(f.1),
                 run_as_base: false,
+                signature: None,
                 origin: o.clone(),
                 fname: None,
                 name: f.0.to_owned(),
@@ -7,6 +7,18 @@ pub const ITER: &str = include_str!("../iter.spl");
 pub const HTTP: &str = include_str!("../http.spl");
 pub const STREAM: &str = include_str!("../stream.spl");
 pub const MESSAGING: &str = include_str!("../messaging.spl");
+pub const ASYNC: &str = include_str!("../async.spl");
+
+/// All embedded files and their names.
+pub const FILES: &[(&str, &str)] = &[
+    ("std.spl", STD),
+    ("net.spl", NET),
+    ("iter.spl", ITER),
+    ("http.spl", HTTP),
+    ("stream.spl", STREAM),
+    ("messaging.spl", MESSAGING),
+    ("async.spl", ASYNC),
+];
 
 pub fn register(runtime: &mut Runtime) {
     multicall! {
@@ -17,5 +29,6 @@ pub fn register(runtime: &mut Runtime) {
         insert("http.spl", HTTP);
         insert("stream.spl", STREAM);
         insert("messaging.spl", MESSAGING);
+        insert("async.spl", ASYNC);
     }
 }
@@ -10,6 +10,9 @@ use std::{
 
 use once_cell::sync::Lazy;
 
+#[cfg(unix)]
+use std::os::fd::AsRawFd;
+
 use crate::{mutex::Mut, runtime::*, *};
 
 static STREAM_TYPES: Lazy<Arc<Mut<HashMap<String, StreamType>>>> =
@@ -48,6 +51,8 @@ pub struct Stream {
     reader: Box<dyn Read + 'static>,
     writer: Box<dyn Write + 'static>,
     close: fn(&mut Self),
+    /// The file descriptor poll-streams waits on. Streams without one are always ready.
+    fd: Option<i32>,
 }
 
 impl Stream {
@@ -60,6 +65,7 @@ impl Stream {
             reader: Box::new(unsafe { mem::transmute::<&mut _, &mut T>(rw.as_mut()) }),
             writer: rw,
             close,
+            fd: None,
         }
     }
     pub fn new_split(
@@ -71,8 +77,15 @@ impl Stream {
             reader: Box::new(reader),
             writer: Box::new(writer),
             close,
+            fd: None,
         }
     }
+
+    /// Sets the file descriptor to wait on when polling this stream for readiness.
+    pub fn with_fd(mut self, fd: i32) -> Self {
+        self.fd = Some(fd);
+        self
+    }
 }
 
 impl Read for Stream {
@@ -140,20 +153,59 @@ pub fn new_stream(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+/// Gets the data to write from a bytes object, or from an array of ints.
+fn buffer_to_write(stack: &mut Stack, buf: AMObject, fn_name: &str) -> Result<Vec<u8>, Error> {
+    let buf = buf.lock_ro();
+    match buf.native {
+        Value::Bytes(ref a) => Ok(a.clone()),
+        Value::Array(ref a) => {
+            let mut fixed = Vec::with_capacity(a.len());
+            for item in a.iter() {
+                match item.lock_ro().native {
+                    Value::Int(x) => fixed.push(x as u8),
+                    _ => type_err!(stack, "!int", "int"),
+                }
+            }
+            Ok(fixed)
+        }
+        _ => stack.err(ErrorKind::InvalidCall(fn_name.to_owned())),
+    }
+}
+
+/// Reads into a bytes object in-place, or into an array of ints, using the given read function.
+fn read_into_buffer(
+    stack: &mut Stack,
+    buf: &AMObject,
+    fn_name: &str,
+    read: impl FnOnce(&mut [u8]) -> std::io::Result<usize>,
+) -> Result<usize, Error> {
+    let mut buf = buf.lock();
+    buf.check_mutable(stack)?;
+    match buf.native {
+        Value::Bytes(ref mut a) => read(&mut a[..]),
+        Value::Array(ref mut a) => {
+            let mut vec = vec![0; a.len()];
+            let r = read(&mut vec[..]);
+            a.clone_from_slice(
+                &vec.into_iter()
+                    .map(|x| Value::Int(x as i32).spl())
+                    .collect::<Vec<_>>(),
+            );
+            r
+        }
+        _ => return stack.err(ErrorKind::InvalidCall(fn_name.to_owned())),
+    }
+    .map_err(|x| stack.error(ErrorKind::IO(format!("{x:?}"))))
+}
+
 pub fn write_stream(stack: &mut Stack) -> OError {
     require_on_stack!(id, Mega, stack, "write-stream");
-    require_array_on_stack!(a, stack, "write-stream");
+    let buf = stack.pop();
     let stream = runtime(|rt| {
         rt.get_stream(id as u128)
             .ok_or_else(|| stack.error(ErrorKind::VariableNotFound(format!("__stream-{id}"))))
     })?;
-    let mut fixed = Vec::with_capacity(a.len());
-    for item in a.iter() {
-        match item.lock_ro().native {
-            Value::Int(x) => fixed.push(x as u8),
-            _ => type_err!(stack, "!int", "int"),
-        }
-    }
+    let fixed = buffer_to_write(stack, buf, "write-stream")?;
     stack.push(
         Value::Mega(
             stream
@@ -168,18 +220,12 @@ pub fn write_stream(stack: &mut Stack) -> OError {
 
 pub fn write_all_stream(stack: &mut Stack) -> OError {
     require_on_stack!(id, Mega, stack, "write-all-stream");
-    require_array_on_stack!(a, stack, "write-all-stream");
+    let buf = stack.pop();
     let stream = runtime(|rt| {
         rt.get_stream(id as u128)
             .ok_or_else(|| stack.error(ErrorKind::VariableNotFound(format!("__stream-{id}"))))
     })?;
-    let mut fixed = Vec::with_capacity(a.len());
-    for item in a.iter() {
-        match item.lock_ro().native {
-            Value::Int(x) => fixed.push(x as u8),
-            _ => type_err!(stack, "!int", "int"),
-        }
-    }
+    let fixed = buffer_to_write(stack, buf, "write-all-stream")?;
     stream
         .lock()
         .write_all(&fixed[..])
@@ -202,55 +248,28 @@ pub fn flush_stream(stack: &mut Stack) -> OError {
 
 pub fn read_stream(stack: &mut Stack) -> OError {
     require_on_stack!(id, Mega, stack, "read-stream");
-    let array = stack.pop();
-    {
-        require_mut_array!(a, array, stack, "read-stream");
-        let stream = runtime(|rt| {
-            rt.get_stream(id as u128)
-                .ok_or_else(|| stack.error(ErrorKind::VariableNotFound(format!("__stream-{id}"))))
-        })?;
-        let mut vec = vec![0; a.len()];
-        stack.push(
-            Value::Mega(
-                stream
-                    .lock()
-                    .read(&mut vec[..])
-                    .map_err(|x| stack.error(ErrorKind::IO(format!("{x:?}"))))?
-                    as i128,
-            )
-            .spl(),
-        );
-        a.clone_from_slice(
-            &vec.into_iter()
-                .map(|x| Value::Int(x as i32).spl())
-                .collect::<Vec<_>>(),
-        );
-    }
-    stack.push(array);
+    let buf = stack.pop();
+    let stream = runtime(|rt| {
+        rt.get_stream(id as u128)
+            .ok_or_else(|| stack.error(ErrorKind::VariableNotFound(format!("__stream-{id}"))))
+    })?;
+    let read = read_into_buffer(stack, &buf, "read-stream", |b| stream.lock().read(b))?;
+    stack.push(Value::Mega(read as i128).spl());
+    stack.push(buf);
     Ok(())
 }
 
 pub fn read_all_stream(stack: &mut Stack) -> OError {
     require_on_stack!(id, Mega, stack, "read-all-stream");
-    let array = stack.pop();
-    {
-        require_mut_array!(a, array, stack, "read-all-stream");
-        let stream = runtime(|rt| {
-            rt.get_stream(id as u128)
-                .ok_or_else(|| stack.error(ErrorKind::VariableNotFound(format!("__stream-{id}"))))
-        })?;
-        let mut vec = vec![0; a.len()];
-        stream
-            .lock()
-            .read_exact(&mut vec[..])
-            .map_err(|x| stack.error(ErrorKind::IO(format!("{x:?}"))))?;
-        a.clone_from_slice(
-            &vec.into_iter()
-                .map(|x| Value::Int(x as i32).spl())
-                .collect::<Vec<_>>(),
-        );
-    }
-    stack.push(array);
+    let buf = stack.pop();
+    let stream = runtime(|rt| {
+        rt.get_stream(id as u128)
+            .ok_or_else(|| stack.error(ErrorKind::VariableNotFound(format!("__stream-{id}"))))
+    })?;
+    read_into_buffer(stack, &buf, "read-all-stream", |b| {
+        stream.lock().read_exact(b).map(|_| b.len())
+    })?;
+    stack.push(buf);
     Ok(())
 }
 
@@ -263,6 +282,90 @@ pub fn close_stream(stack: &mut Stack) -> OError {
     Ok(())
 }
 
+/// Waits until one of the file descriptors is ready to be read from, or written to if its flag
+/// is set, or until the timeout in milliseconds has passed if it isn't negative. Returns which of
+/// them are ready.
+#[cfg(unix)]
+fn poll_fds(fds: &[(i32, bool)], timeout: i32) -> std::io::Result<Vec<bool>> {
+    #[repr(C)]
+    struct PollFd {
+        fd: i32,
+        events: i16,
+        revents: i16,
+    }
+    #[cfg(target_os = "linux")]
+    type NFds = std::ffi::c_ulong;
+    #[cfg(not(target_os = "linux"))]
+    type NFds = std::ffi::c_uint;
+    extern "C" {
+        fn poll(fds: *mut PollFd, nfds: NFds, timeout: i32) -> i32;
+    }
+    const POLLIN: i16 = 1;
+    const POLLOUT: i16 = 4;
+
+    let mut pollfds: Vec<_> = fds
+        .iter()
+        .map(|&(fd, write)| PollFd {
+            fd,
+            events: if write { POLLOUT } else { POLLIN },
+            revents: 0,
+        })
+        .collect();
+    // SAFETY: The pointer and length describe pollfds, which poll only writes revents of.
+    while unsafe { poll(pollfds.as_mut_ptr(), pollfds.len() as NFds, timeout) } < 0 {
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+    Ok(pollfds.iter().map(|x| x.revents != 0).collect())
+}
+
+/// Without poll, streams can't have file descriptors, so this only waits for the timeout.
+#[cfg(not(unix))]
+fn poll_fds(fds: &[(i32, bool)], timeout: i32) -> std::io::Result<Vec<bool>> {
+    if fds.is_empty() && timeout > 0 {
+        std::thread::sleep(std::time::Duration::from_millis(timeout as u64));
+    }
+    Ok(vec![true; fds.len()])
+}
+
+pub fn poll_streams(stack: &mut Stack) -> OError {
+    require_on_stack!(timeout, Mega, stack, "poll-streams");
+    require_on_stack!(writes, Array, stack, "poll-streams");
+    require_on_stack!(reads, Array, stack, "poll-streams");
+    let mut ready = Vec::new();
+    let mut waiting = Vec::new();
+    for (ids, write) in [(reads, false), (writes, true)] {
+        for id in ids {
+            let Value::Mega(id) = id.lock_ro().native else {
+                return stack.err(ErrorKind::InvalidCall("poll-streams".to_owned()));
+            };
+            let stream = runtime(|rt| {
+                rt.get_stream(id as u128).ok_or_else(|| {
+                    stack.error(ErrorKind::VariableNotFound(format!("__stream-{id}")))
+                })
+            })?;
+            let fd = stream.lock_ro().fd;
+            match fd {
+                Some(fd) => waiting.push((id, fd, write)),
+                None => ready.push(id),
+            }
+        }
+    }
+    let timeout = if ready.is_empty() {
+        timeout.clamp(-1, i32::MAX as i128) as i32
+    } else {
+        0
+    };
+    let fds: Vec<_> = waiting.iter().map(|x| (x.1, x.2)).collect();
+    let polled =
+        poll_fds(&fds, timeout).map_err(|x| stack.error(ErrorKind::IO(format!("{x:?}"))))?;
+    ready.extend(waiting.iter().zip(polled).filter(|x| x.1).map(|x| x.0 .0));
+    stack.push(Value::Array(ready.into_iter().map(|x| Value::Mega(x).spl()).collect()).spl());
+    Ok(())
+}
+
 fn nop(_stream: &mut Stream) {}
 
 fn stream_file(stack: &mut Stack) -> Result<Stream, Error> {
@@ -285,17 +388,20 @@ fn stream_tcp(stack: &mut Stack) -> Result<Stream, Error> {
     require_on_stack!(ip, Str, stack, "TCP new-stream");
     fn close_tcp(stream: &mut Stream) {
         unsafe {
-            let f = ((stream.reader.as_mut() as *mut dyn Read).cast() as *mut TcpStream)
+            let f = ((stream.reader.as_mut() as *mut dyn Read).cast::<TcpStream>())
                 .as_mut()
                 .unwrap();
             let _ = f.shutdown(Shutdown::Both);
         }
     }
-    Ok(Stream::new(
-        TcpStream::connect((ip, port as u16))
-            .map_err(|x| stack.error(ErrorKind::IO(x.to_string())))?,
-        close_tcp,
-    ))
+    let tcp = TcpStream::connect((ip, port as u16))
+        .map_err(|x| stack.error(ErrorKind::IO(x.to_string())))?;
+    #[cfg(unix)]
+    let fd = tcp.as_raw_fd();
+    let stream = Stream::new(tcp, close_tcp);
+    #[cfg(unix)]
+    let stream = stream.with_fd(fd);
+    Ok(stream)
 }
 
 fn stream_udp(stack: &mut Stack) -> Result<Stream, Error> {
@@ -323,7 +429,12 @@ fn stream_udp(stack: &mut Stack) -> Result<Stream, Error> {
             self.0.recv(buf)
         }
     }
-    Ok(Stream::new(UdpRW(sock), close_udp))
+    #[cfg(unix)]
+    let fd = sock.as_raw_fd();
+    let stream = Stream::new(UdpRW(sock), close_udp);
+    #[cfg(unix)]
+    let stream = stream.with_fd(fd);
+    Ok(stream)
 }
 
 pub fn register(r: &mut Stack, o: Arc<Frame>) {
@@ -335,7 +446,7 @@ pub fn register(r: &mut Stack, o: Arc<Frame>) {
     }
 
     type Fn = fn(&mut Stack) -> OError;
-    let fns: [(&str, Fn, u32); 7] = [
+    let fns: [(&str, Fn, u32); 8] = [
         ("new-stream", new_stream, 1),
         ("write-stream", write_stream, 1),
         ("write-all-stream", write_all_stream, 0),
@@ -343,6 +454,7 @@ pub fn register(r: &mut Stack, o: Arc<Frame>) {
         ("read-stream", read_stream, 1),
         ("read-all-stream", read_all_stream, 0),
         ("close-stream", close_stream, 0),
+        ("poll-streams", poll_streams, 1),
     ];
     for f in fns {
         r.define_func(
@@ -351,6 +463,7 @@ pub fn register(r: &mut Stack, o: Arc<Frame>) {
                 ret_count: f.2,
                 to_call: FuncImpl::Native(f.1),
                 run_as_base: false,
+                signature: None,
                 origin: o.clone(),
                 fname: None,
                 name: f.0.to_owned(),